@@ -1,21 +1,46 @@
 use anchor_lang::prelude::*;
+use anchor_lang::Discriminator;
 
 #[account]
 pub struct RuinStaking {
-    pub distributor_bump: u8,
     pub ruin_staking_bump: u8,
+    // Last seed of the [b"staking", staking_token, admin, pool_id] PDA, lets one
+    // admin run several pools on the same mint
+    pub pool_id: u64,
     pub staking_admin: Pubkey,
     pub staking_token: Pubkey,
-    pub distributor: Pubkey,
     pub treasury: Pubkey,
+    // Bump of the [b"treasury-authority", ruin_staking] PDA owning the treasury
+    pub treasury_authority_bump: u8,
+    // Bump of the [b"vault-authority", ruin_staking] PDA owning the distributors
+    pub vault_authority_bump: u8,
     pub total_staked: u128,
     pub paused: bool,
+    // Number of terms registered on this pool, also the index of the next term
+    pub term_count: u8,
+    // Admin proposed by propose_admin, waiting for accept_admin
+    pub pending_admin: Option<Pubkey>,
+    // Optional role that can only toggle paused
+    pub pauser: Option<Pubkey>,
+    // Optional role that can only fund distributors and change APR
+    pub reward_manager: Option<Pubkey>,
+    // Principal penalties kept in the treasury by PenaltyDestination::Treasury terms,
+    // not owed to any investor until swept by the admin
+    pub penalty_collected: u64,
 }
 
 #[account]
 pub struct RuinStakingTerm {
     pub ruin_staking: Pubkey,
     pub ruin_staking_term_bump: u8,
+    pub term_index: u8,
+    pub distributor_bump: u8,
+    pub distributor: Pubkey,
+    // Mint held by the distributor, rewards are paid in this token
+    pub reward_mint: Pubkey,
+    // Reward tokens earned per staked token at the term APR, as a ratio
+    pub reward_rate_numerator: u64,
+    pub reward_rate_denominator: u64,
     pub minimum_staking_amount: u64,
     pub maximum_staking_amount: u64,
     pub start_join_time: i64,
@@ -23,7 +48,144 @@ pub struct RuinStakingTerm {
     pub lock_duration: i64,
     pub delay_duration: i64,
     pub apr: u32,
+    // How rewards behave once a stake's lock has ended
+    pub post_lock_accrual: PostLockAccrual,
+    // Only used with PostLockAccrual::PostLockApr
+    pub post_lock_apr: u32,
+    // Maximum number of concurrent stakers, 0 means unlimited
     pub total_slot: u8,
+    pub active_stakers: u32,
+    pub total_staked: u128,
+    // Cumulative rewards, scaled by DIV_PRECISION like pending_rewards
+    pub total_rewards_accrued: u128,
+    pub total_rewards_paid: u128,
+    // Unclaimed rewards plus rewards reserved until lock end, scaled by DIV_PRECISION
+    pub reward_liability: u128,
+    pub total_funded: u64,
+    pub early_withdraw_enabled: bool,
+    // Share of withdrawn principal forfeited when leaving before lock end
+    pub early_withdraw_penalty_bps: u16,
+    // Share of unclaimed rewards forfeited when leaving before lock end
+    pub early_withdraw_reward_penalty_bps: u16,
+    pub penalty_destination: PenaltyDestination,
+    // Token account receiving principal penalties with PenaltyDestination::FeeAccount
+    pub penalty_fee_account: Pubkey,
+    // Minimum notice between queue_term_change and its effective_at
+    pub term_change_delay: i64,
+    // Ring of the latest APR changes, apr_checkpoint_head is the next slot to write
+    pub apr_checkpoints: [AprCheckpoint; APR_CHECKPOINT_CAPACITY],
+    pub apr_checkpoint_count: u8,
+    pub apr_checkpoint_head: u8,
+    pub reward_mode: RewardMode,
+    // Emission mode only: rewards released per second, shared by stake weight
+    pub reward_per_second: u64,
+    // Emission mode only: rewards per staked token, scaled by DIV_PRECISION
+    pub acc_reward_per_share: u128,
+    pub last_reward_time: i64,
+    pub emission_end_time: i64,
+    // Number of extra reward streams, also the index of the next stream
+    pub reward_stream_count: u8,
+    // Grows with every APR raise so reservations made at a lower APR follow it,
+    // scaled by DIV_PRECISION
+    pub reserve_index: u128,
+    // Stakers' reservations valued at reserve_index, part of reward_liability
+    pub total_reserved_reward: u128,
+    // Emission added to acc_reward_per_share but not yet harvested by stakers,
+    // scaled by DIV_PRECISION
+    pub emitted_unharvested: u128,
+}
+
+pub const APR_CHECKPOINT_CAPACITY: usize = 8;
+pub const MAX_REWARD_STREAMS: u8 = 4;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RewardMode {
+    // Each staker earns the term APR on their own principal
+    FixedApr,
+    // A fixed budget is emitted per second and split pro-rata by stake
+    Emission,
+}
+
+// APR in force from `timestamp` until the next checkpoint
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AprCheckpoint {
+    pub timestamp: i64,
+    pub apr: u32,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PostLockAccrual {
+    // Rewards stop at the end of the lock
+    Stop,
+    // Rewards continue at the term's post_lock_apr
+    PostLockApr,
+    // Rewards continue at the term's apr
+    SameApr,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PenaltyDestination {
+    // Principal penalties stay in the treasury
+    Treasury,
+    // Principal penalties fund the term's distributor
+    Distributor,
+    // Principal penalties go to the term's penalty_fee_account
+    FeeAccount,
+}
+
+// Term values queued by queue_term_change, applied once effective_at is reached
+#[account]
+pub struct PendingTermChange {
+    pub pending_term_change_bump: u8,
+    pub ruin_staking_term: Pubkey,
+    pub minimum_staking_amount: u64,
+    pub maximum_staking_amount: u64,
+    pub start_join_time: i64,
+    pub end_join_time: i64,
+    pub delay_duration: i64,
+    pub apr: u32,
+    pub term_change_delay: i64,
+    pub effective_at: i64,
+    pub post_lock_accrual: PostLockAccrual,
+    pub post_lock_apr: u32,
+}
+
+// Extra reward token paid to the term's stakers on top of the term rewards,
+// typically funded by a partner
+#[account]
+pub struct RewardStream {
+    pub reward_stream_bump: u8,
+    pub ruin_staking_term: Pubkey,
+    pub stream_index: u8,
+    pub reward_mint: Pubkey,
+    pub distributor_bump: u8,
+    pub distributor: Pubkey,
+    pub reward_mode: RewardMode,
+    // FixedApr only: reward tokens per staked token, in basis points per year
+    pub apr: u32,
+    // Emission only: rewards released per second, shared by stake weight
+    pub reward_per_second: u64,
+    // Emission only: rewards per staked token, scaled by DIV_PRECISION
+    pub acc_reward_per_share: u128,
+    pub last_reward_time: i64,
+    pub start_time: i64,
+    // Nothing accrues after end_time in either mode
+    pub end_time: i64,
+    pub total_funded: u64,
+    pub total_rewards_accrued: u128,
+    pub total_rewards_paid: u128,
+}
+
+// Investor's position in a reward stream, settled whenever their principal changes
+#[account]
+pub struct UserRewardStream {
+    pub user_reward_stream_bump: u8,
+    pub reward_stream: Pubkey,
+    pub investor: Pubkey,
+    pub pending_rewards: u128,
+    pub reward_debt: u128,
+    pub updated_time: i64,
+    pub claimable_at: i64,
 }
 
 #[account]
@@ -32,6 +194,12 @@ pub struct UserStaked {
     pub total_staked: u64,
     pub join_time: i64,
     pub updated_time: i64,
+    // Future rewards until lock end already counted in the term's reward_liability
+    pub reserved_reward: u128,
+    // Term reserve_index when reserved_reward was set
+    pub reserve_index: u128,
+    // Emission mode only: total_staked * acc_reward_per_share at the last update
+    pub reward_debt: u128,
 }
 
 #[account]
@@ -57,26 +225,81 @@ const STAKING_AMOUNT_LENGTH: usize = 128;
 const TIME_EPOCH_LENGTH: usize = 64;
 const APR_KEY_LENGTH: usize = 32;
 const TOTAL_SLOT_LENGTH: usize = 8;
+const STAKER_COUNT_LENGTH: usize = 32;
+const TERM_INDEX_LENGTH: usize = 8;
+const PENALTY_BPS_LENGTH: usize = 16;
 
 impl RuinStaking {
     pub const LEN: usize = DISCRIMINATOR_LENGTH
-        + PUBLIC_KEY_LENGTH * 4
+        + PUBLIC_KEY_LENGTH * 3
         + STAKING_AMOUNT_LENGTH
         + BOOL_LENGTH
-        + BUMP_LENGTH;
+        + BUMP_LENGTH
+        + TERM_INDEX_LENGTH
+        + (BOOL_LENGTH + PUBLIC_KEY_LENGTH) * 3
+        + BUMP_LENGTH
+        + TIME_EPOCH_LENGTH
+        + BUMP_LENGTH
+        + STAKING_AMOUNT_LENGTH;
 }
 
 impl RuinStakingTerm {
     pub const LEN: usize = DISCRIMINATOR_LENGTH
-        + PUBLIC_KEY_LENGTH
+        + PUBLIC_KEY_LENGTH * 3
         + TIME_EPOCH_LENGTH * 6
-        + APR_KEY_LENGTH
+        + APR_KEY_LENGTH * 3
         + TOTAL_SLOT_LENGTH
-        + BUMP_LENGTH;
+        + STAKER_COUNT_LENGTH
+        + STAKING_AMOUNT_LENGTH * 4
+        + TIME_EPOCH_LENGTH
+        + TERM_INDEX_LENGTH
+        + BUMP_LENGTH * 2
+        + BOOL_LENGTH * 2
+        + PENALTY_BPS_LENGTH * 2
+        + TIME_EPOCH_LENGTH
+        + (TIME_EPOCH_LENGTH + APR_KEY_LENGTH) * APR_CHECKPOINT_CAPACITY
+        + TERM_INDEX_LENGTH * 2
+        + BOOL_LENGTH
+        + TIME_EPOCH_LENGTH * 3
+        + STAKING_AMOUNT_LENGTH
+        + PUBLIC_KEY_LENGTH
+        + TIME_EPOCH_LENGTH * 2
+        + TERM_INDEX_LENGTH
+        + STAKING_AMOUNT_LENGTH * 2;
+}
+
+impl RewardStream {
+    pub const LEN: usize = DISCRIMINATOR_LENGTH
+        + BUMP_LENGTH * 2
+        + PUBLIC_KEY_LENGTH * 3
+        + TERM_INDEX_LENGTH
+        + BOOL_LENGTH
+        + APR_KEY_LENGTH
+        + TIME_EPOCH_LENGTH * 5
+        + STAKING_AMOUNT_LENGTH * 3;
+}
+
+impl UserRewardStream {
+    pub const LEN: usize = DISCRIMINATOR_LENGTH
+        + BUMP_LENGTH
+        + PUBLIC_KEY_LENGTH * 2
+        + STAKING_AMOUNT_LENGTH * 2
+        + TIME_EPOCH_LENGTH * 2;
+}
+
+impl PendingTermChange {
+    pub const LEN: usize = DISCRIMINATOR_LENGTH
+        + BUMP_LENGTH
+        + PUBLIC_KEY_LENGTH
+        + TIME_EPOCH_LENGTH * 8
+        + APR_KEY_LENGTH
+        + BOOL_LENGTH
+        + APR_KEY_LENGTH;
 }
 
 impl UserStaked {
-    pub const LEN: usize = DISCRIMINATOR_LENGTH + BUMP_LENGTH + TIME_EPOCH_LENGTH * 3;
+    pub const LEN: usize =
+        DISCRIMINATOR_LENGTH + BUMP_LENGTH + TIME_EPOCH_LENGTH * 3 + STAKING_AMOUNT_LENGTH * 3;
 }
 
 impl PendingWithdrawl {
@@ -87,3 +310,97 @@ impl PendingRewardWithdrawl {
     pub const LEN: usize =
         DISCRIMINATOR_LENGTH + BUMP_LENGTH + TIME_EPOCH_LENGTH + STAKING_AMOUNT_LENGTH;
 }
+
+// Layouts of pools and terms created before they moved to seeded addresses.
+// They kept the discriminators of the current accounts, so they are told apart
+// by their allocated size, and are only ever read.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct LegacyRuinStaking {
+    pub distributor_bump: u8,
+    pub ruin_staking_bump: u8,
+    pub staking_admin: Pubkey,
+    pub staking_token: Pubkey,
+    pub distributor: Pubkey,
+    pub treasury: Pubkey,
+    pub total_staked: u128,
+    pub paused: bool,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct LegacyRuinStakingTerm {
+    pub ruin_staking: Pubkey,
+    pub ruin_staking_term_bump: u8,
+    pub minimum_staking_amount: u64,
+    pub maximum_staking_amount: u64,
+    pub start_join_time: i64,
+    pub end_join_time: i64,
+    pub lock_duration: i64,
+    pub delay_duration: i64,
+    pub apr: u32,
+    pub total_slot: u8,
+}
+
+impl LegacyRuinStaking {
+    pub const LEN: usize = DISCRIMINATOR_LENGTH
+        + PUBLIC_KEY_LENGTH * 4
+        + STAKING_AMOUNT_LENGTH
+        + BOOL_LENGTH
+        + BUMP_LENGTH;
+}
+
+impl LegacyRuinStakingTerm {
+    pub const LEN: usize = DISCRIMINATOR_LENGTH
+        + PUBLIC_KEY_LENGTH
+        + TIME_EPOCH_LENGTH * 6
+        + APR_KEY_LENGTH
+        + TOTAL_SLOT_LENGTH
+        + BUMP_LENGTH;
+}
+
+fn deserialize_legacy<T: AnchorDeserialize>(
+    buf: &mut &[u8],
+    discriminator: [u8; 8],
+    len: usize,
+) -> Result<T> {
+    if buf.len() != len || buf[..8] != discriminator {
+        return Err(ErrorCode::AccountDiscriminatorMismatch.into());
+    }
+    let mut data: &[u8] = &buf[8..];
+    AnchorDeserialize::deserialize(&mut data).map_err(|_| ErrorCode::AccountDidNotDeserialize.into())
+}
+
+impl AccountSerialize for LegacyRuinStaking {}
+
+impl AccountDeserialize for LegacyRuinStaking {
+    fn try_deserialize(buf: &mut &[u8]) -> Result<Self> {
+        deserialize_legacy(buf, RuinStaking::discriminator(), Self::LEN)
+    }
+
+    fn try_deserialize_unchecked(buf: &mut &[u8]) -> Result<Self> {
+        Self::try_deserialize(buf)
+    }
+}
+
+impl Owner for LegacyRuinStaking {
+    fn owner() -> Pubkey {
+        crate::ID
+    }
+}
+
+impl AccountSerialize for LegacyRuinStakingTerm {}
+
+impl AccountDeserialize for LegacyRuinStakingTerm {
+    fn try_deserialize(buf: &mut &[u8]) -> Result<Self> {
+        deserialize_legacy(buf, RuinStakingTerm::discriminator(), Self::LEN)
+    }
+
+    fn try_deserialize_unchecked(buf: &mut &[u8]) -> Result<Self> {
+        Self::try_deserialize(buf)
+    }
+}
+
+impl Owner for LegacyRuinStakingTerm {
+    fn owner() -> Pubkey {
+        crate::ID
+    }
+}
@@ -1,98 +1,1118 @@
 use crate::{id};
 use crate::error::*;
 use crate::account::*;
+use crate::helpers::*;
 use anchor_lang::prelude::*;
-use anchor_spl::token::{Mint, Token, TokenAccount, Transfer, SetAuthority};
+use anchor_spl::token::{CloseAccount, Mint, Token, TokenAccount, Transfer};
 
 
 #[derive(Accounts)]
-#[instruction(minimum_staking_amount: u64, maximum_staking_amount: u64, lock_duration: i64)]
+#[instruction(pool_id: u64)]
 pub struct InitializeStakingInfo<'info> {
     #[account(
         init,
+        seeds = [
+            b"staking",
+            ruin_staking_token.key().as_ref(),
+            ruin_staking_admin.key().as_ref(),
+            pool_id.to_le_bytes().as_ref()
+        ],
         space = RuinStaking::LEN,
         payer = ruin_staking_admin, 
+        bump,
+    )]
+    pub ruin_staking: Box<Account<'info, RuinStaking>>,
+
+    #[account(
+        init,
+        seeds = [
+            b"term",
+            ruin_staking.key().as_ref(),
+            [0u8].as_ref()
+        ],
+        space = RuinStakingTerm::LEN,
+        payer = ruin_staking_admin,
+        bump,
+    )]
+    pub ruin_staking_term: Box<Account<'info, RuinStakingTerm>>,
+
+    #[account(mut)]
+    pub ruin_staking_admin: Signer<'info>,
+
+    pub ruin_staking_token: Account<'info, Mint>,
+
+    // Same as ruin_staking_token when rewards are paid in the staked token
+    pub ruin_staking_reward_token: Account<'info, Mint>,
+
+    /// CHECK: PDA owning the treasury, validated by seeds
+    #[account(
+        seeds = [TREASURY_AUTHORITY_PDA_SEED, ruin_staking.key().as_ref()],
+        bump,
+    )]
+    pub treasury_authority: AccountInfo<'info>,
+
+    /// CHECK: PDA owning the pool's distributors, validated by seeds
+    #[account(
+        seeds = [VAULT_AUTHORITY_PDA_SEED, ruin_staking.key().as_ref()],
+        bump,
+    )]
+    pub vault_authority: AccountInfo<'info>,
+
+    #[account( 
+        init,
+        payer = ruin_staking_admin,
+        token::mint = ruin_staking_token,
+        token::authority = treasury_authority,
+    )]
+    pub ruin_staking_treasury: Account<'info, TokenAccount>,
+
+    #[account( 
+        init,
+        seeds = [
+            b"distributor",
+            ruin_staking_term.key().as_ref()
+        ],
+        payer = ruin_staking_admin,
+        bump,
+        token::mint = ruin_staking_reward_token,
+        token::authority = vault_authority,
+    )]
+    pub ruin_staking_distributor: Account<'info, TokenAccount>,
+
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct AddStakingTerm<'info> {
+    #[account(
+        mut,
+        owner = id(),
+        constraint = ruin_staking.staking_admin == ruin_staking_admin.key() @ProgramErrorCode::InvalidStakingAdmin
+    )]
+    pub ruin_staking: Account<'info, RuinStaking>,
+
+    #[account(
+        init,
+        seeds = [
+            b"term",
+            ruin_staking.key().as_ref(),
+            [ruin_staking.term_count].as_ref()
+        ],
+        space = RuinStakingTerm::LEN,
+        payer = ruin_staking_admin,
+        bump,
+    )]
+    pub ruin_staking_term: Box<Account<'info, RuinStakingTerm>>,
+
+    #[account(mut)]
+    pub ruin_staking_admin: Signer<'info>,
+
+    #[account(
+        constraint = ruin_staking_token.key() == ruin_staking.staking_token @ProgramErrorCode::UnsupportedStakingToken
+    )]
+    pub ruin_staking_token: Account<'info, Mint>,
+
+    pub ruin_staking_reward_token: Account<'info, Mint>,
+
+    /// CHECK: PDA owning the pool's distributors, validated by seeds
+    #[account(
+        seeds = [VAULT_AUTHORITY_PDA_SEED, ruin_staking.key().as_ref()],
+        bump = ruin_staking.vault_authority_bump,
+    )]
+    pub vault_authority: AccountInfo<'info>,
+
+    #[account( 
+        init,
+        seeds = [
+            b"distributor",
+            ruin_staking_term.key().as_ref()
+        ],
+        payer = ruin_staking_admin,
+        bump,
+        token::mint = ruin_staking_reward_token,
+        token::authority = vault_authority,
+    )]
+    pub ruin_staking_distributor: Account<'info, TokenAccount>,
+
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct SetPaused<'info> {
+    #[account(
+        mut,
+        owner = id(),
+        constraint = is_pauser(&ruin_staking, &authority.key()) @ProgramErrorCode::InvalidPauser
+    )]
+    pub ruin_staking: Account<'info, RuinStaking>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ProposeAdmin<'info> {
+    #[account(
+        mut,
+        owner = id(),
+        constraint = ruin_staking.staking_admin == ruin_staking_admin.key() @ProgramErrorCode::InvalidStakingAdmin
+    )]
+    pub ruin_staking: Account<'info, RuinStaking>,
+
+    pub ruin_staking_admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptAdmin<'info> {
+    #[account(
+        mut,
+        owner = id(),
+        constraint = ruin_staking.pending_admin == Some(pending_admin.key()) @ProgramErrorCode::InvalidPendingAdmin
+    )]
+    pub ruin_staking: Account<'info, RuinStaking>,
+
+    pub pending_admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetRoles<'info> {
+    #[account(
+        mut,
+        owner = id(),
+        constraint = ruin_staking.staking_admin == ruin_staking_admin.key() @ProgramErrorCode::InvalidStakingAdmin
+    )]
+    pub ruin_staking: Account<'info, RuinStaking>,
+
+    pub ruin_staking_admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateStakingTerm<'info> {
+    #[account(
+        owner = id(),
+        constraint = ruin_staking.staking_admin == ruin_staking_admin.key() @ProgramErrorCode::InvalidStakingAdmin
+    )]
+    pub ruin_staking: Account<'info, RuinStaking>,
+
+    #[account(
+        mut,
+        owner = id(),
+        seeds = [
+            b"term",
+            ruin_staking.key().as_ref(),
+            [ruin_staking_term.term_index].as_ref()
+        ],
+        bump = ruin_staking_term.ruin_staking_term_bump,
+        has_one = ruin_staking @ProgramErrorCode::InvalidStakingTerms
+    )]
+    pub ruin_staking_term: Account<'info, RuinStakingTerm>,
+
+    #[account(
+        constraint = distributor_token_account.key() == ruin_staking_term.distributor @ProgramErrorCode::WrongDistributorAddress,
+    )]
+    pub distributor_token_account: Box<Account<'info, TokenAccount>>,
+
+    pub ruin_staking_admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct QueueTermChange<'info> {
+    #[account(
+        owner = id(),
+        constraint = ruin_staking.staking_admin == ruin_staking_admin.key() @ProgramErrorCode::InvalidStakingAdmin
+    )]
+    pub ruin_staking: Account<'info, RuinStaking>,
+
+    #[account(
+        owner = id(),
+        seeds = [
+            b"term",
+            ruin_staking.key().as_ref(),
+            [ruin_staking_term.term_index].as_ref()
+        ],
+        bump = ruin_staking_term.ruin_staking_term_bump,
+        has_one = ruin_staking @ProgramErrorCode::InvalidStakingTerms
+    )]
+    pub ruin_staking_term: Account<'info, RuinStakingTerm>,
+
+    #[account(
+        init,
+        seeds = [b"term_change", ruin_staking_term.key().as_ref()],
+        payer = ruin_staking_admin,
+        space = PendingTermChange::LEN,
+        bump,
+    )]
+    pub pending_term_change: Account<'info, PendingTermChange>,
+
+    #[account(mut)]
+    pub ruin_staking_admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteTermChange<'info> {
+    #[account(
+        owner = id(),
+        constraint = ruin_staking.staking_admin == ruin_staking_admin.key() @ProgramErrorCode::InvalidStakingAdmin
+    )]
+    pub ruin_staking: Account<'info, RuinStaking>,
+
+    #[account(
+        mut,
+        owner = id(),
+        seeds = [
+            b"term",
+            ruin_staking.key().as_ref(),
+            [ruin_staking_term.term_index].as_ref()
+        ],
+        bump = ruin_staking_term.ruin_staking_term_bump,
+        has_one = ruin_staking @ProgramErrorCode::InvalidStakingTerms
+    )]
+    pub ruin_staking_term: Account<'info, RuinStakingTerm>,
+
+    #[account(
+        constraint = distributor_token_account.key() == ruin_staking_term.distributor @ProgramErrorCode::WrongDistributorAddress,
+    )]
+    pub distributor_token_account: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        close = ruin_staking_admin,
+        seeds = [b"term_change", ruin_staking_term.key().as_ref()],
+        bump = pending_term_change.pending_term_change_bump,
+    )]
+    pub pending_term_change: Account<'info, PendingTermChange>,
+
+    #[account(mut)]
+    pub ruin_staking_admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CancelTermChange<'info> {
+    #[account(
+        owner = id(),
+        constraint = ruin_staking.staking_admin == ruin_staking_admin.key() @ProgramErrorCode::InvalidStakingAdmin
+    )]
+    pub ruin_staking: Account<'info, RuinStaking>,
+
+    #[account(
+        owner = id(),
+        seeds = [
+            b"term",
+            ruin_staking.key().as_ref(),
+            [ruin_staking_term.term_index].as_ref()
+        ],
+        bump = ruin_staking_term.ruin_staking_term_bump,
+        has_one = ruin_staking @ProgramErrorCode::InvalidStakingTerms
+    )]
+    pub ruin_staking_term: Account<'info, RuinStakingTerm>,
+
+    #[account(
+        mut,
+        close = ruin_staking_admin,
+        seeds = [b"term_change", ruin_staking_term.key().as_ref()],
+        bump = pending_term_change.pending_term_change_bump,
+    )]
+    pub pending_term_change: Account<'info, PendingTermChange>,
+
+    #[account(mut)]
+    pub ruin_staking_admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateApr<'info> {
+    #[account(
+        owner = id(),
+        constraint = is_reward_manager(&ruin_staking, &authority.key()) @ProgramErrorCode::InvalidRewardManager
+    )]
+    pub ruin_staking: Account<'info, RuinStaking>,
+
+    #[account(
+        mut,
+        owner = id(),
+        seeds = [
+            b"term",
+            ruin_staking.key().as_ref(),
+            [ruin_staking_term.term_index].as_ref()
+        ],
+        bump = ruin_staking_term.ruin_staking_term_bump,
+        has_one = ruin_staking @ProgramErrorCode::InvalidStakingTerms
+    )]
+    pub ruin_staking_term: Account<'info, RuinStakingTerm>,
+
+    #[account(
+        constraint = distributor_token_account.key() == ruin_staking_term.distributor @ProgramErrorCode::WrongDistributorAddress,
+    )]
+    pub distributor_token_account: Box<Account<'info, TokenAccount>>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetRewardExchangeRate<'info> {
+    #[account(
+        owner = id(),
+        constraint = is_reward_manager(&ruin_staking, &authority.key()) @ProgramErrorCode::InvalidRewardManager
+    )]
+    pub ruin_staking: Account<'info, RuinStaking>,
+
+    #[account(
+        mut,
+        owner = id(),
+        seeds = [
+            b"term",
+            ruin_staking.key().as_ref(),
+            [ruin_staking_term.term_index].as_ref()
+        ],
+        bump = ruin_staking_term.ruin_staking_term_bump,
+        has_one = ruin_staking @ProgramErrorCode::InvalidStakingTerms
+    )]
+    pub ruin_staking_term: Account<'info, RuinStakingTerm>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ConfigureEmission<'info> {
+    #[account(
+        owner = id(),
+        constraint = is_reward_manager(&ruin_staking, &authority.key()) @ProgramErrorCode::InvalidRewardManager
+    )]
+    pub ruin_staking: Account<'info, RuinStaking>,
+
+    #[account(
+        mut,
+        owner = id(),
+        seeds = [
+            b"term",
+            ruin_staking.key().as_ref(),
+            [ruin_staking_term.term_index].as_ref()
+        ],
+        bump = ruin_staking_term.ruin_staking_term_bump,
+        has_one = ruin_staking @ProgramErrorCode::InvalidStakingTerms
+    )]
+    pub ruin_staking_term: Account<'info, RuinStakingTerm>,
+
+    #[account(
+        constraint = distributor_token_account.key() == ruin_staking_term.distributor @ProgramErrorCode::WrongDistributorAddress,
+    )]
+    pub distributor_token_account: Account<'info, TokenAccount>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetPostLockAccrual<'info> {
+    #[account(
+        owner = id(),
+        constraint = ruin_staking.staking_admin == ruin_staking_admin.key() @ProgramErrorCode::InvalidStakingAdmin
+    )]
+    pub ruin_staking: Account<'info, RuinStaking>,
+
+    #[account(
+        mut,
+        owner = id(),
+        seeds = [
+            b"term",
+            ruin_staking.key().as_ref(),
+            [ruin_staking_term.term_index].as_ref()
+        ],
+        bump = ruin_staking_term.ruin_staking_term_bump,
+        has_one = ruin_staking @ProgramErrorCode::InvalidStakingTerms
+    )]
+    pub ruin_staking_term: Account<'info, RuinStakingTerm>,
+
+    pub ruin_staking_admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetEarlyWithdrawConfig<'info> {
+    #[account(
+        owner = id(),
+        constraint = ruin_staking.staking_admin == ruin_staking_admin.key() @ProgramErrorCode::InvalidStakingAdmin
+    )]
+    pub ruin_staking: Account<'info, RuinStaking>,
+
+    #[account(
+        mut,
+        owner = id(),
+        seeds = [
+            b"term",
+            ruin_staking.key().as_ref(),
+            [ruin_staking_term.term_index].as_ref()
+        ],
+        bump = ruin_staking_term.ruin_staking_term_bump,
+        has_one = ruin_staking @ProgramErrorCode::InvalidStakingTerms
+    )]
+    pub ruin_staking_term: Account<'info, RuinStakingTerm>,
+
+    #[account(
+        owner = Token::id(),
+        constraint = penalty_fee_account.mint.key() == ruin_staking.staking_token.key() @ProgramErrorCode::UnsupportedStakingToken
+    )]
+    pub penalty_fee_account: Account<'info, TokenAccount>,
+
+    pub ruin_staking_admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SweepPenalties<'info> {
+    #[account(
+        mut,
+        owner = id(),
+        constraint = ruin_staking.staking_admin == ruin_staking_admin.key() @ProgramErrorCode::InvalidStakingAdmin
+    )]
+    pub ruin_staking: Account<'info, RuinStaking>,
+
+    /// CHECK: PDA owning the treasury, validated by seeds
+    #[account(
+        seeds = [TREASURY_AUTHORITY_PDA_SEED, ruin_staking.key().as_ref()],
+        bump = ruin_staking.treasury_authority_bump,
+    )]
+    pub treasury_authority: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        constraint = treasury_token_account.key() == ruin_staking.treasury.key() @ProgramErrorCode::WrongTreasuryAddress,
+    )]
+    pub treasury_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        owner = Token::id(),
+        constraint = recipient_token_account.mint.key() == ruin_staking.staking_token.key() @ProgramErrorCode::UnsupportedStakingToken
+    )]
+    pub recipient_token_account: Account<'info, TokenAccount>,
+
+    pub ruin_staking_admin: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct FundDistributor<'info> {
+    #[account(
+        owner = id(),
+        constraint = is_reward_manager(&ruin_staking, &authority.key()) @ProgramErrorCode::InvalidRewardManager
+    )]
+    pub ruin_staking: Account<'info, RuinStaking>,
+
+    #[account(
+        mut,
+        owner = id(),
+        seeds = [
+            b"term",
+            ruin_staking.key().as_ref(),
+            [ruin_staking_term.term_index].as_ref()
+        ],
+        bump = ruin_staking_term.ruin_staking_term_bump,
+        has_one = ruin_staking @ProgramErrorCode::InvalidStakingTerms
+    )]
+    pub ruin_staking_term: Account<'info, RuinStakingTerm>,
+
+    #[account(
+        mut,
+        constraint = distributor_token_account.key() == ruin_staking_term.distributor @ProgramErrorCode::WrongDistributorAddress,
+    )]
+    pub distributor_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        owner = Token::id(),
+        constraint = funder_token_account.owner.key() == authority.key() @ProgramErrorCode::InvalidTokenAccountOwnership,
+        constraint = funder_token_account.mint.key() == ruin_staking_term.reward_mint @ProgramErrorCode::UnsupportedRewardToken
+    )]
+    pub funder_token_account: Account<'info, TokenAccount>,
+
+    pub authority: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+// Moves the rewards left in a pool created before pools and terms lived at
+// seeded addresses, whose distributor seed truncated the lock duration to one
+// byte, into a term of a pool created with the current layout.
+#[derive(Accounts)]
+pub struct MigrateDistributor<'info> {
+    #[account(
+        owner = id(),
+        constraint = ruin_staking.staking_admin == ruin_staking_admin.key() @ProgramErrorCode::InvalidStakingAdmin
+    )]
+    pub ruin_staking: Account<'info, RuinStaking>,
+
+    #[account(
+        mut,
+        owner = id(),
+        seeds = [
+            b"term",
+            ruin_staking.key().as_ref(),
+            [ruin_staking_term.term_index].as_ref()
+        ],
+        bump = ruin_staking_term.ruin_staking_term_bump,
+        has_one = ruin_staking @ProgramErrorCode::InvalidStakingTerms
+    )]
+    pub ruin_staking_term: Account<'info, RuinStakingTerm>,
+
+    #[account(
+        constraint = legacy_ruin_staking.staking_admin == ruin_staking_admin.key() @ProgramErrorCode::InvalidStakingAdmin
+    )]
+    pub legacy_ruin_staking: Box<Account<'info, LegacyRuinStaking>>,
+
+    #[account(
+        constraint = legacy_ruin_staking_term.ruin_staking == legacy_ruin_staking.key() @ProgramErrorCode::InvalidStakingTerms
+    )]
+    pub legacy_ruin_staking_term: Box<Account<'info, LegacyRuinStakingTerm>>,
+
+    /// CHECK: Program-wide PDA that owned every distributor before vault
+    /// authorities were scoped per pool, validated by seeds
+    #[account(
+        seeds = [VAULT_AUTHORITY_PDA_SEED],
+        bump,
+    )]
+    pub legacy_vault_authority: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"distributor",
+            legacy_ruin_staking.staking_token.as_ref(),
+            legacy_ruin_staking.staking_admin.as_ref(),
+            [legacy_ruin_staking_term.lock_duration as u8].as_ref()
+        ],
+        bump = legacy_ruin_staking.distributor_bump,
+        constraint = legacy_distributor.mint == ruin_staking_term.reward_mint @ProgramErrorCode::UnsupportedRewardToken
+    )]
+    pub legacy_distributor: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = distributor_token_account.key() == ruin_staking_term.distributor @ProgramErrorCode::WrongDistributorAddress,
+    )]
+    pub distributor_token_account: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub ruin_staking_admin: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct AddRewardStream<'info> {
+    #[account(
+        owner = id(),
+        constraint = ruin_staking.staking_admin == ruin_staking_admin.key() @ProgramErrorCode::InvalidStakingAdmin
+    )]
+    pub ruin_staking: Account<'info, RuinStaking>,
+
+    #[account(
+        mut,
+        owner = id(),
+        seeds = [
+            b"term",
+            ruin_staking.key().as_ref(),
+            [ruin_staking_term.term_index].as_ref()
+        ],
+        bump = ruin_staking_term.ruin_staking_term_bump,
+        has_one = ruin_staking @ProgramErrorCode::InvalidStakingTerms,
+        constraint = ruin_staking_term.reward_stream_count < MAX_REWARD_STREAMS @ProgramErrorCode::RewardStreamLimitReached
+    )]
+    pub ruin_staking_term: Account<'info, RuinStakingTerm>,
+
+    #[account(
+        init,
+        seeds = [
+            b"reward_stream",
+            ruin_staking_term.key().as_ref(),
+            [ruin_staking_term.reward_stream_count].as_ref()
+        ],
+        payer = ruin_staking_admin,
+        space = RewardStream::LEN,
+        bump,
+    )]
+    pub reward_stream: Box<Account<'info, RewardStream>>,
+
+    pub reward_mint: Account<'info, Mint>,
+
+    /// CHECK: PDA owning the distributor, validated by seeds
+    #[account(
+        seeds = [VAULT_AUTHORITY_PDA_SEED, ruin_staking.key().as_ref()],
+        bump = ruin_staking.vault_authority_bump,
+    )]
+    pub vault_authority: AccountInfo<'info>,
+
+    #[account(
+        init,
+        seeds = [
+            b"stream_distributor",
+            reward_stream.key().as_ref()
+        ],
+        payer = ruin_staking_admin,
+        bump,
+        token::mint = reward_mint,
+        token::authority = vault_authority,
+    )]
+    pub reward_stream_distributor: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub ruin_staking_admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct FundRewardStream<'info> {
+    #[account(
+        owner = id(),
+        constraint = is_reward_manager(&ruin_staking, &authority.key()) @ProgramErrorCode::InvalidRewardManager
+    )]
+    pub ruin_staking: Account<'info, RuinStaking>,
+
+    #[account(
+        owner = id(),
+        seeds = [
+            b"term",
+            ruin_staking.key().as_ref(),
+            [ruin_staking_term.term_index].as_ref()
+        ],
+        bump = ruin_staking_term.ruin_staking_term_bump,
+        has_one = ruin_staking @ProgramErrorCode::InvalidStakingTerms
+    )]
+    pub ruin_staking_term: Account<'info, RuinStakingTerm>,
+
+    #[account(
+        mut,
+        owner = id(),
+        seeds = [
+            b"reward_stream",
+            ruin_staking_term.key().as_ref(),
+            [reward_stream.stream_index].as_ref()
+        ],
+        bump = reward_stream.reward_stream_bump,
+        has_one = ruin_staking_term @ProgramErrorCode::InvalidRewardStream
+    )]
+    pub reward_stream: Account<'info, RewardStream>,
+
+    #[account(
+        mut,
+        constraint = distributor_token_account.key() == reward_stream.distributor @ProgramErrorCode::WrongDistributorAddress,
+    )]
+    pub distributor_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        owner = Token::id(),
+        constraint = funder_token_account.owner.key() == authority.key() @ProgramErrorCode::InvalidTokenAccountOwnership,
+        constraint = funder_token_account.mint.key() == reward_stream.reward_mint @ProgramErrorCode::UnsupportedRewardToken
+    )]
+    pub funder_token_account: Account<'info, TokenAccount>,
+
+    pub authority: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct JoinRewardStream<'info> {
+    #[account(owner = id())]
+    pub ruin_staking: Account<'info, RuinStaking>,
+
+    #[account(
+        owner = id(),
+        seeds = [
+            b"term",
+            ruin_staking.key().as_ref(),
+            [ruin_staking_term.term_index].as_ref()
+        ],
+        bump = ruin_staking_term.ruin_staking_term_bump,
+        has_one = ruin_staking @ProgramErrorCode::InvalidStakingTerms
+    )]
+    pub ruin_staking_term: Account<'info, RuinStakingTerm>,
+
+    #[account(
+        owner = id(),
+        seeds = [
+            b"reward_stream",
+            ruin_staking_term.key().as_ref(),
+            [reward_stream.stream_index].as_ref()
+        ],
+        bump = reward_stream.reward_stream_bump,
+        has_one = ruin_staking_term @ProgramErrorCode::InvalidRewardStream
+    )]
+    pub reward_stream: Account<'info, RewardStream>,
+
+    #[account(
+        init,
+        seeds = [
+            b"stream_reward",
+            reward_stream.key().as_ref(),
+            investor.key().as_ref()
+        ],
+        payer = investor,
+        space = UserRewardStream::LEN,
+        bump,
+    )]
+    pub user_reward_stream: Box<Account<'info, UserRewardStream>>,
+
+    #[account(mut)]
+    pub investor: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimPendingReward<'info> {
+    #[account(
+        owner = id(),
+        constraint = !ruin_staking.paused @ProgramErrorCode::StakingPaused,
+    )]
+    pub ruin_staking: Account<'info, RuinStaking>,
+
+    #[account(
+        mut,
+        owner = id(),
+        seeds = [
+            b"term",
+            ruin_staking.key().as_ref(),
+            [ruin_staking_term.term_index].as_ref()
+        ],
+        bump = ruin_staking_term.ruin_staking_term_bump,
+        has_one = ruin_staking @ProgramErrorCode::InvalidStakingTerms
+    )]
+    pub ruin_staking_term: Account<'info, RuinStakingTerm>,
+
+    /// CHECK: PDA owning the distributors, validated by seeds
+    #[account(
+        seeds = [VAULT_AUTHORITY_PDA_SEED, ruin_staking.key().as_ref()],
+        bump = ruin_staking.vault_authority_bump,
+    )]
+    pub vault_authority: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"withdraw_reward",
+            ruin_staking.key().as_ref(),
+            ruin_staking_term.key().as_ref(),
+            investor.key().as_ref()
+        ],
+        bump = user_pending_withdrawl.pending_reward_withdrawl_bump,  
+    )]
+    pub user_pending_withdrawl: Box<Account<'info, PendingRewardWithdrawl>>,
+
+    #[account(
+        mut,
+        owner = Token::id(),
+        constraint = distributor_token_account.key() == ruin_staking_term.distributor @ProgramErrorCode::WrongDistributorAddress,
+    )]
+    pub distributor_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        owner = Token::id(),
+        constraint = investor_token_account.owner.key() == investor.key() @ProgramErrorCode::InvalidTokenAccountOwnership,
+        constraint = investor_token_account.mint.key() == ruin_staking_term.reward_mint @ProgramErrorCode::UnsupportedRewardToken
+    )]
+    pub investor_token_account: Account<'info, TokenAccount>,
+    pub investor: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct Withdraw<'info> {
+    #[account(
+        mut,
+        owner = id(),
+        constraint = !ruin_staking.paused @ProgramErrorCode::StakingPaused,
+    )]
+    pub ruin_staking: Account<'info, RuinStaking>,
+
+    #[account(
+        mut,
+        owner = id(),
+        seeds = [
+            b"term",
+            ruin_staking.key().as_ref(),
+            [ruin_staking_term.term_index].as_ref()
+        ],
+        bump = ruin_staking_term.ruin_staking_term_bump,
+        has_one = ruin_staking @ProgramErrorCode::InvalidStakingTerms
+    )]
+    pub ruin_staking_term: Account<'info, RuinStakingTerm>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"stake",
+            ruin_staking.key().as_ref(),
+            ruin_staking_term.key().as_ref(),
+            investor.key().as_ref()
+        ],
+        bump = user_staked.user_staked_bump, 
+    )]
+    pub user_staked: Box<Account<'info, UserStaked>>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"withdraw_reward",
+            ruin_staking.key().as_ref(),
+            ruin_staking_term.key().as_ref(),
+            investor.key().as_ref()
+        ],
+        bump = user_pending_withdrawl.pending_reward_withdrawl_bump,  
+    )]
+    pub user_pending_withdrawl: Box<Account<'info, PendingRewardWithdrawl>>,
+
+    #[account(
+        init_if_needed,
+        seeds = [
+            b"withdraw",
+            ruin_staking.key().as_ref(),
+            ruin_staking_term.key().as_ref(),
+            investor.key().as_ref()
+        ],
+        payer = investor,
+        space = PendingWithdrawl::LEN,
+        bump, 
+    )]
+    pub user_pending_withdraw: Box<Account<'info, PendingWithdrawl>>,
+
+    #[account(
+        mut,
+        constraint = treasury_token_account.key() == ruin_staking.treasury.key() @ProgramErrorCode::WrongTreasuryAddress,
+    )]
+    pub treasury_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        owner = Token::id(),
+        constraint = investor_token_account.owner.key() == investor.key() @ProgramErrorCode::InvalidTokenAccountOwnership,
+        constraint = investor_token_account.mint.key() == ruin_staking.staking_token.key() @ProgramErrorCode::UnsupportedStakingToken
+    )]
+    pub investor_token_account: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub investor: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct EarlyWithdraw<'info> {
+    #[account(
+        mut,
+        owner = id(),
+        constraint = !ruin_staking.paused @ProgramErrorCode::StakingPaused,
+    )]
+    pub ruin_staking: Account<'info, RuinStaking>,
+
+    #[account(
+        mut,
+        owner = id(),
+        seeds = [
+            b"term",
+            ruin_staking.key().as_ref(),
+            [ruin_staking_term.term_index].as_ref()
+        ],
+        bump = ruin_staking_term.ruin_staking_term_bump,
+        has_one = ruin_staking @ProgramErrorCode::InvalidStakingTerms
+    )]
+    pub ruin_staking_term: Account<'info, RuinStakingTerm>,
+
+    /// CHECK: PDA owning the treasury, validated by seeds
+    #[account(
+        seeds = [TREASURY_AUTHORITY_PDA_SEED, ruin_staking.key().as_ref()],
+        bump = ruin_staking.treasury_authority_bump,
+    )]
+    pub treasury_authority: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"stake",
+            ruin_staking.key().as_ref(),
+            ruin_staking_term.key().as_ref(),
+            investor.key().as_ref()
+        ],
+        bump = user_staked.user_staked_bump,
+    )]
+    pub user_staked: Box<Account<'info, UserStaked>>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"withdraw_reward",
+            ruin_staking.key().as_ref(),
+            ruin_staking_term.key().as_ref(),
+            investor.key().as_ref()
+        ],
+        bump = user_pending_withdrawl.pending_reward_withdrawl_bump,
+    )]
+    pub user_pending_withdrawl: Box<Account<'info, PendingRewardWithdrawl>>,
+
+    #[account(
+        mut,
+        constraint = treasury_token_account.key() == ruin_staking.treasury.key() @ProgramErrorCode::WrongTreasuryAddress,
+    )]
+    pub treasury_token_account: Box<Account<'info, TokenAccount>>,
+
+    // Treasury, term distributor or term fee account depending on penalty_destination
+    #[account(
+        mut,
+        constraint = penalty_token_account.key() == penalty_destination_address(&ruin_staking, &ruin_staking_term) @ProgramErrorCode::WrongPenaltyAddress,
+    )]
+    pub penalty_token_account: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        owner = Token::id(),
+        constraint = investor_token_account.owner.key() == investor.key() @ProgramErrorCode::InvalidTokenAccountOwnership,
+        constraint = investor_token_account.mint.key() == ruin_staking.staking_token.key() @ProgramErrorCode::UnsupportedStakingToken
+    )]
+    pub investor_token_account: Account<'info, TokenAccount>,
+
+    pub investor: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct EmergencyWithdraw<'info> {
+    #[account(
+        mut,
+        owner = id(),
+        constraint = ruin_staking.paused @ProgramErrorCode::StakingNotPaused,
+    )]
+    pub ruin_staking: Account<'info, RuinStaking>,
+
+    #[account(
+        mut,
+        owner = id(),
+        seeds = [
+            b"term",
+            ruin_staking.key().as_ref(),
+            [ruin_staking_term.term_index].as_ref()
+        ],
+        bump = ruin_staking_term.ruin_staking_term_bump,
+        has_one = ruin_staking @ProgramErrorCode::InvalidStakingTerms
     )]
-    pub ruin_staking: Box<Account<'info, RuinStaking>>,
+    pub ruin_staking_term: Account<'info, RuinStakingTerm>,
 
+    /// CHECK: PDA owning the treasury, validated by seeds
     #[account(
-        init,
-        space = RuinStakingTerm::LEN,
-        payer = ruin_staking_admin,
+        seeds = [TREASURY_AUTHORITY_PDA_SEED, ruin_staking.key().as_ref()],
+        bump = ruin_staking.treasury_authority_bump,
     )]
-    pub ruin_staking_term: Box<Account<'info, RuinStakingTerm>>,
-
-    #[account(mut)]
-    pub ruin_staking_admin: Signer<'info>,
+    pub treasury_authority: AccountInfo<'info>,
 
-    pub ruin_staking_token: Account<'info, Mint>,
+    #[account(
+        mut,
+        close = investor,
+        seeds = [
+            b"stake",
+            ruin_staking.key().as_ref(),
+            ruin_staking_term.key().as_ref(),
+            investor.key().as_ref()
+        ],
+        bump = user_staked.user_staked_bump,
+    )]
+    pub user_staked: Box<Account<'info, UserStaked>>,
 
-    #[account( 
-        init,
-        payer = ruin_staking_admin,
-        token::mint = ruin_staking_token,
-        token::authority = ruin_staking,
+    #[account(
+        mut,
+        close = investor,
+        seeds = [
+            b"withdraw_reward",
+            ruin_staking.key().as_ref(),
+            ruin_staking_term.key().as_ref(),
+            investor.key().as_ref()
+        ],
+        bump = user_pending_withdrawl.pending_reward_withdrawl_bump,
     )]
-    pub ruin_staking_treasury: Account<'info, TokenAccount>,
+    pub user_pending_withdrawl: Box<Account<'info, PendingRewardWithdrawl>>,
 
-    #[account( 
-        init,
+    /// CHECK: PendingWithdrawl PDA validated by seeds, only exists once the investor has withdrawn
+    #[account(
+        mut,
         seeds = [
-            b"distributor",
-            ruin_staking_token.key().as_ref(),
-            ruin_staking_admin.key().as_ref(),
-            [lock_duration as u8].as_ref()
+            b"withdraw",
+            ruin_staking.key().as_ref(),
+            ruin_staking_term.key().as_ref(),
+            investor.key().as_ref()
         ],
-        payer = ruin_staking_admin,
         bump,
-        token::mint = ruin_staking_token,
-        token::authority = ruin_staking_admin,
     )]
-    pub ruin_staking_distributor: Account<'info, TokenAccount>,
+    pub user_pending_withdraw: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        constraint = treasury_token_account.key() == ruin_staking.treasury.key() @ProgramErrorCode::WrongTreasuryAddress,
+    )]
+    pub treasury_token_account: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        owner = Token::id(),
+        constraint = investor_token_account.owner.key() == investor.key() @ProgramErrorCode::InvalidTokenAccountOwnership,
+        constraint = investor_token_account.mint.key() == ruin_staking.staking_token.key() @ProgramErrorCode::UnsupportedStakingToken
+    )]
+    pub investor_token_account: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub investor: Signer<'info>,
 
-    pub system_program: Program<'info, System>,
     pub token_program: Program<'info, Token>,
-    pub rent: Sysvar<'info, Rent>,
 }
 
 #[derive(Accounts)]
-pub struct ClaimPendingReward<'info> {
+pub struct ClaimPendingWithdrawl<'info> {
     #[account(owner = id())]
     pub ruin_staking: Account<'info, RuinStaking>,
 
     #[account(
         owner = id(),
+        seeds = [
+            b"term",
+            ruin_staking.key().as_ref(),
+            [ruin_staking_term.term_index].as_ref()
+        ],
+        bump = ruin_staking_term.ruin_staking_term_bump,
         has_one = ruin_staking @ProgramErrorCode::InvalidStakingTerms
     )]
     pub ruin_staking_term: Account<'info, RuinStakingTerm>,
 
-    /// CHECK: No need to check right here
-    pub vault_authority: AccountInfo<'info>,
+    /// CHECK: PDA owning the treasury, validated by seeds
+    #[account(
+        seeds = [TREASURY_AUTHORITY_PDA_SEED, ruin_staking.key().as_ref()],
+        bump = ruin_staking.treasury_authority_bump,
+    )]
+    pub treasury_authority: AccountInfo<'info>,
 
     #[account(
         mut,
         seeds = [
-            b"withdraw_reward",
+            b"withdraw",
             ruin_staking.key().as_ref(),
             ruin_staking_term.key().as_ref(),
             investor.key().as_ref()
         ],
-        bump = user_pending_withdrawl.pending_reward_withdrawl_bump,  
+        bump = user_pending_withdraw.pending_withdrawl_bump,
     )]
-    pub user_pending_withdrawl: Box<Account<'info, PendingRewardWithdrawl>>,
+    pub user_pending_withdraw: Box<Account<'info, PendingWithdrawl>>,
 
     #[account(
         mut,
-        owner = Token::id(),
-        seeds = [
-            b"distributor",
-            ruin_staking.staking_token.key().as_ref(),
-            ruin_staking.staking_admin.key().as_ref(),
-            &[ruin_staking_term.lock_duration as u8]
-        ],
-        bump = ruin_staking.distributor_bump,  
+        constraint = treasury_token_account.key() == ruin_staking.treasury.key() @ProgramErrorCode::WrongTreasuryAddress,
     )]
-    pub distributor_token_account: Account<'info, TokenAccount>,
+    pub treasury_token_account: Account<'info, TokenAccount>,
 
     #[account(
         mut,
@@ -107,18 +1127,36 @@ pub struct ClaimPendingReward<'info> {
 }
 
 #[derive(Accounts)]
-pub struct Withdraw<'info> {
+pub struct Compound<'info> {
     #[account(
+        mut,
         owner = id(),
+        constraint = !ruin_staking.paused @ProgramErrorCode::StakingPaused,
     )]
     pub ruin_staking: Account<'info, RuinStaking>,
 
+    // Rewards can only be restaked when they are paid in the staking token
     #[account(
+        mut,
         owner = id(),
-        has_one = ruin_staking @ProgramErrorCode::InvalidStakingTerms
+        seeds = [
+            b"term",
+            ruin_staking.key().as_ref(),
+            [ruin_staking_term.term_index].as_ref()
+        ],
+        bump = ruin_staking_term.ruin_staking_term_bump,
+        has_one = ruin_staking @ProgramErrorCode::InvalidStakingTerms,
+        constraint = ruin_staking_term.reward_mint == ruin_staking.staking_token @ProgramErrorCode::RewardMintMismatch
     )]
     pub ruin_staking_term: Account<'info, RuinStakingTerm>,
 
+    /// CHECK: PDA owning the distributor, validated by seeds
+    #[account(
+        seeds = [VAULT_AUTHORITY_PDA_SEED, ruin_staking.key().as_ref()],
+        bump = ruin_staking.vault_authority_bump,
+    )]
+    pub vault_authority: AccountInfo<'info>,
+
     #[account(
         mut,
         seeds = [
@@ -127,54 +1165,117 @@ pub struct Withdraw<'info> {
             ruin_staking_term.key().as_ref(),
             investor.key().as_ref()
         ],
-        bump = user_staked.user_staked_bump, 
+        bump = user_staked.user_staked_bump,
     )]
     pub user_staked: Box<Account<'info, UserStaked>>,
 
     #[account(
-        init,
+        mut,
         seeds = [
-            b"withdraw",
+            b"withdraw_reward",
             ruin_staking.key().as_ref(),
             ruin_staking_term.key().as_ref(),
             investor.key().as_ref()
         ],
-        payer = investor,
-        space = PendingRewardWithdrawl::LEN,
-        bump, 
+        bump = user_pending_withdrawl.pending_reward_withdrawl_bump,
     )]
-    pub user_pending_withdraw: Box<Account<'info, PendingWithdrawl>>,
+    pub user_pending_withdrawl: Box<Account<'info, PendingRewardWithdrawl>>,
 
     #[account(
         mut,
-        constraint = treasury_token_account.key() == ruin_staking.treasury.key() @ProgramErrorCode::WrongTreasuryAddress,
+        constraint = distributor_token_account.key() == ruin_staking_term.distributor @ProgramErrorCode::WrongDistributorAddress,
     )]
-    pub treasury_token_account: Account<'info, TokenAccount>,
+    pub distributor_token_account: Box<Account<'info, TokenAccount>>,
 
     #[account(
         mut,
-        owner = Token::id(),
-        constraint = investor_token_account.owner.key() == investor.key() @ProgramErrorCode::InvalidTokenAccountOwnership,
-        constraint = investor_token_account.mint.key() == ruin_staking.staking_token.key() @ProgramErrorCode::UnsupportedStakingToken
+        constraint = treasury_token_account.key() == ruin_staking.treasury.key() @ProgramErrorCode::WrongTreasuryAddress,
     )]
-    pub investor_token_account: Account<'info, TokenAccount>,
+    pub treasury_token_account: Box<Account<'info, TokenAccount>>,
 
-    #[account(mut)]
     pub investor: Signer<'info>,
 
-    pub system_program: Program<'info, System>,
     pub token_program: Program<'info, Token>,
 }
 
+#[derive(Accounts)]
+pub struct ClosePosition<'info> {
+    #[account(owner = id())]
+    pub ruin_staking: Account<'info, RuinStaking>,
+
+    #[account(
+        mut,
+        owner = id(),
+        seeds = [
+            b"term",
+            ruin_staking.key().as_ref(),
+            [ruin_staking_term.term_index].as_ref()
+        ],
+        bump = ruin_staking_term.ruin_staking_term_bump,
+        has_one = ruin_staking @ProgramErrorCode::InvalidStakingTerms
+    )]
+    pub ruin_staking_term: Account<'info, RuinStakingTerm>,
+
+    #[account(
+        mut,
+        close = investor,
+        seeds = [
+            b"stake",
+            ruin_staking.key().as_ref(),
+            ruin_staking_term.key().as_ref(),
+            investor.key().as_ref()
+        ],
+        bump = user_staked.user_staked_bump,
+    )]
+    pub user_staked: Box<Account<'info, UserStaked>>,
+
+    #[account(
+        mut,
+        close = investor,
+        seeds = [
+            b"withdraw_reward",
+            ruin_staking.key().as_ref(),
+            ruin_staking_term.key().as_ref(),
+            investor.key().as_ref()
+        ],
+        bump = user_pending_withdrawl.pending_reward_withdrawl_bump,
+    )]
+    pub user_pending_withdrawl: Box<Account<'info, PendingRewardWithdrawl>>,
+
+    /// CHECK: PendingWithdrawl PDA validated by seeds, only exists once the investor has withdrawn
+    #[account(
+        mut,
+        seeds = [
+            b"withdraw",
+            ruin_staking.key().as_ref(),
+            ruin_staking_term.key().as_ref(),
+            investor.key().as_ref()
+        ],
+        bump,
+    )]
+    pub user_pending_withdraw: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub investor: Signer<'info>,
+}
+
 #[derive(Accounts)]
 pub struct Harvest<'info> {
     #[account(
         owner = id(),
+        constraint = !ruin_staking.paused @ProgramErrorCode::StakingPaused,
     )]
     pub ruin_staking: Account<'info, RuinStaking>,
 
     #[account(
+        mut,
         owner = id(),
+        seeds = [
+            b"term",
+            ruin_staking.key().as_ref(),
+            [ruin_staking_term.term_index].as_ref()
+        ],
+        bump = ruin_staking_term.ruin_staking_term_bump,
         has_one = ruin_staking @ProgramErrorCode::InvalidStakingTerms
     )]
     pub ruin_staking_term: Account<'info, RuinStakingTerm>,
@@ -217,12 +1318,21 @@ pub struct Harvest<'info> {
 #[derive(Accounts)]
 pub struct Stake<'info> {
     #[account(
+        mut,
         owner = id(),
+        constraint = !ruin_staking.paused @ProgramErrorCode::StakingPaused,
     )]
     pub ruin_staking: Account<'info, RuinStaking>,
 
     #[account(
+        mut,
         owner = id(),
+        seeds = [
+            b"term",
+            ruin_staking.key().as_ref(),
+            [ruin_staking_term.term_index].as_ref()
+        ],
+        bump = ruin_staking_term.ruin_staking_term_bump,
         has_one = ruin_staking @ProgramErrorCode::InvalidStakingTerms
     )]
     pub ruin_staking_term: Account<'info, RuinStakingTerm>,
@@ -266,9 +1376,14 @@ pub struct Stake<'info> {
     #[account(
         mut,
         owner = Token::id(),
-        constraint = treasury_token_account.owner == ruin_staking.key()
+        constraint = treasury_token_account.key() == ruin_staking.treasury.key() @ProgramErrorCode::WrongTreasuryAddress,
     )]
     pub treasury_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        constraint = distributor_token_account.key() == ruin_staking_term.distributor @ProgramErrorCode::WrongDistributorAddress,
+    )]
+    pub distributor_token_account: Account<'info, TokenAccount>,
     
     #[account(mut)]
     pub investor: Signer<'info>,
@@ -278,16 +1393,6 @@ pub struct Stake<'info> {
     pub rent: Sysvar<'info, Rent>,
 }
 
-impl<'info> InitializeStakingInfo<'info> {
-    pub fn into_set_authority_context(&self) -> CpiContext<'_, '_, '_, 'info, SetAuthority<'info>> {
-        let cpi_accounts = SetAuthority {
-            account_or_mint: self.ruin_staking_distributor.to_account_info().clone(),
-            current_authority: self.ruin_staking_admin.to_account_info().clone(),
-        };
-        CpiContext::new(self.token_program.to_account_info().clone(), cpi_accounts)
-    }
-}
-
 impl<'info> Stake<'info> {
     pub fn into_transfer_token_to_treasury(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
         let cpi_accounts = Transfer {
@@ -300,6 +1405,52 @@ impl<'info> Stake<'info> {
     }
 }
 
+impl<'info> FundDistributor<'info> {
+    pub fn into_transfer_token_to_distributor(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        let cpi_accounts = Transfer {
+            from: self.funder_token_account.to_account_info(),
+            to: self.distributor_token_account.to_account_info(),
+            authority: self.authority.to_account_info()
+        };
+
+        CpiContext::new(self.token_program.to_account_info().clone(), cpi_accounts)
+    }
+}
+
+impl<'info> MigrateDistributor<'info> {
+    pub fn into_transfer_to_distributor(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        let cpi_accounts = Transfer {
+            from: self.legacy_distributor.to_account_info(),
+            to: self.distributor_token_account.to_account_info(),
+            authority: self.legacy_vault_authority.to_account_info()
+        };
+
+        CpiContext::new(self.token_program.to_account_info().clone(), cpi_accounts)
+    }
+
+    pub fn into_close_legacy_distributor(&self) -> CpiContext<'_, '_, '_, 'info, CloseAccount<'info>> {
+        let cpi_accounts = CloseAccount {
+            account: self.legacy_distributor.to_account_info(),
+            destination: self.ruin_staking_admin.to_account_info(),
+            authority: self.legacy_vault_authority.to_account_info()
+        };
+
+        CpiContext::new(self.token_program.to_account_info().clone(), cpi_accounts)
+    }
+}
+
+impl<'info> FundRewardStream<'info> {
+    pub fn into_transfer_token_to_distributor(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        let cpi_accounts = Transfer {
+            from: self.funder_token_account.to_account_info(),
+            to: self.distributor_token_account.to_account_info(),
+            authority: self.authority.to_account_info()
+        };
+
+        CpiContext::new(self.token_program.to_account_info().clone(), cpi_accounts)
+    }
+}
+
 impl<'info> ClaimPendingReward<'info> {
     pub fn into_transfer_reward_to_investor(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
         let cpi_accounts = Transfer {
@@ -312,6 +1463,73 @@ impl<'info> ClaimPendingReward<'info> {
     }
 }
 
-impl<'info> Withdraw<'info> {
+impl<'info> ClaimPendingWithdrawl<'info> {
+    pub fn into_transfer_principal_to_investor(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        let cpi_accounts = Transfer {
+            from: self.treasury_token_account.to_account_info(),
+            to: self.investor_token_account.to_account_info(),
+            authority: self.treasury_authority.to_account_info()
+        };
+
+        CpiContext::new(self.token_program.to_account_info().clone(), cpi_accounts)
+    }
+}
+
+
+impl<'info> EarlyWithdraw<'info> {
+    pub fn into_transfer_principal_to_investor(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        let cpi_accounts = Transfer {
+            from: self.treasury_token_account.to_account_info(),
+            to: self.investor_token_account.to_account_info(),
+            authority: self.treasury_authority.to_account_info()
+        };
+
+        CpiContext::new(self.token_program.to_account_info().clone(), cpi_accounts)
+    }
+
+    pub fn into_transfer_penalty(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        let cpi_accounts = Transfer {
+            from: self.treasury_token_account.to_account_info(),
+            to: self.penalty_token_account.to_account_info(),
+            authority: self.treasury_authority.to_account_info()
+        };
+
+        CpiContext::new(self.token_program.to_account_info().clone(), cpi_accounts)
+    }
+}
+
+impl<'info> SweepPenalties<'info> {
+    pub fn into_transfer_penalties_to_recipient(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        let cpi_accounts = Transfer {
+            from: self.treasury_token_account.to_account_info(),
+            to: self.recipient_token_account.to_account_info(),
+            authority: self.treasury_authority.to_account_info()
+        };
+
+        CpiContext::new(self.token_program.to_account_info().clone(), cpi_accounts)
+    }
+}
+
+impl<'info> EmergencyWithdraw<'info> {
+    pub fn into_transfer_principal_to_investor(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        let cpi_accounts = Transfer {
+            from: self.treasury_token_account.to_account_info(),
+            to: self.investor_token_account.to_account_info(),
+            authority: self.treasury_authority.to_account_info()
+        };
+
+        CpiContext::new(self.token_program.to_account_info().clone(), cpi_accounts)
+    }
 }
 
+impl<'info> Compound<'info> {
+    pub fn into_transfer_reward_to_treasury(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        let cpi_accounts = Transfer {
+            from: self.distributor_token_account.to_account_info(),
+            to: self.treasury_token_account.to_account_info(),
+            authority: self.vault_authority.to_account_info()
+        };
+
+        CpiContext::new(self.token_program.to_account_info().clone(), cpi_accounts)
+    }
+}
@@ -26,4 +26,68 @@ pub enum ProgramErrorCode {
     InvalidJoiningTime,
     #[msg("Staking term did not matched with Staking info!")]
     InvalidStakingTerms,
+    #[msg("Only staking admin can perform this action!")]
+    InvalidStakingAdmin,
+    #[msg("Staking system is paused!")]
+    StakingPaused,
+    #[msg("Minimum staking amount must not exceed maximum staking amount!")]
+    InvalidStakingAmountRange,
+    #[msg("All staking slots of this term are taken!")]
+    StakingSlotsFull,
+    #[msg("Distributor does not hold enough rewards for this stake!")]
+    InsufficientRewardFunds,
+    #[msg("Wrong distributor address!")]
+    WrongDistributorAddress,
+    #[msg("Reward calculation overflowed!")]
+    MathOverflow,
+    #[msg("Staking is still locked!")]
+    StakingLocked,
+    #[msg("Staking lock has already ended!")]
+    StakingUnlocked,
+    #[msg("Early withdraw is not enabled for this term!")]
+    EarlyWithdrawDisabled,
+    #[msg("Penalty must not exceed 10000 basis points!")]
+    InvalidPenaltyBps,
+    #[msg("Wrong penalty destination address!")]
+    WrongPenaltyAddress,
+    #[msg("Staking system is not paused!")]
+    StakingNotPaused,
+    #[msg("Position still holds principal or rewards!")]
+    PositionNotEmpty,
+    #[msg("Only the proposed admin can accept the admin role!")]
+    InvalidPendingAdmin,
+    #[msg("Only staking admin or pauser can perform this action!")]
+    InvalidPauser,
+    #[msg("Only staking admin or reward manager can perform this action!")]
+    InvalidRewardManager,
+    #[msg("Changes that hurt existing stakers need queue_term_change!")]
+    TermChangeRequiresTimelock,
+    #[msg("Term change must respect the term change delay!")]
+    TermChangeTooEarly,
+    #[msg("Term change is not effective yet!")]
+    TermChangeNotReady,
+    #[msg("Emission must not end in the past!")]
+    InvalidEmissionSchedule,
+    #[msg("Reward mode can't change while the term has stakers!")]
+    RewardModeLocked,
+    #[msg("Reward exchange rate must be greater than zero!")]
+    InvalidRewardExchangeRate,
+    #[msg("Reward exchange rate can't change while the term has stakers or unpaid rewards!")]
+    RewardExchangeRateLocked,
+    #[msg("Reward token does not match the term reward mint!")]
+    UnsupportedRewardToken,
+    #[msg("Rewards are not paid in the staking token!")]
+    RewardMintMismatch,
+    #[msg("Term already has the maximum number of reward streams!")]
+    RewardStreamLimitReached,
+    #[msg("Reward stream accounts are missing, out of order or not owned by the investor!")]
+    InvalidRewardStream,
+    #[msg("Term change delay is below the minimum!")]
+    InvalidTermChangeDelay,
+    #[msg("No withdrawn tokens are waiting to be claimed!")]
+    NothingToClaim,
+    #[msg("Queued withdrawal must be claimed before withdrawing again!")]
+    WithdrawalAlreadyQueued,
+    #[msg("Staking already has the maximum number of terms!")]
+    TermLimitReached,
 }
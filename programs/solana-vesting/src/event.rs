@@ -0,0 +1,302 @@
+use crate::account::{PenaltyDestination, PostLockAccrual, RewardMode};
+use anchor_lang::prelude::*;
+
+#[event]
+pub struct Initialized {
+    pub ruin_staking: Pubkey,
+    pub pool_id: u64,
+    pub ruin_staking_term: Pubkey,
+    pub staking_admin: Pubkey,
+    pub staking_token: Pubkey,
+    pub reward_mint: Pubkey,
+    pub treasury: Pubkey,
+    pub distributor: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct StakingTermAdded {
+    pub ruin_staking: Pubkey,
+    pub ruin_staking_term: Pubkey,
+    pub term_index: u8,
+    pub reward_mint: Pubkey,
+    pub distributor: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct StakingTermUpdated {
+    pub ruin_staking: Pubkey,
+    pub ruin_staking_term: Pubkey,
+    pub minimum_staking_amount: u64,
+    pub maximum_staking_amount: u64,
+    pub start_join_time: i64,
+    pub end_join_time: i64,
+    pub delay_duration: i64,
+    pub apr: u32,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PostLockAccrualUpdated {
+    pub ruin_staking: Pubkey,
+    pub ruin_staking_term: Pubkey,
+    pub post_lock_accrual: PostLockAccrual,
+    pub post_lock_apr: u32,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct EarlyWithdrawConfigUpdated {
+    pub ruin_staking: Pubkey,
+    pub ruin_staking_term: Pubkey,
+    pub enabled: bool,
+    pub penalty_bps: u16,
+    pub reward_penalty_bps: u16,
+    pub penalty_destination: PenaltyDestination,
+    pub penalty_fee_account: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct TermChangeQueued {
+    pub ruin_staking: Pubkey,
+    pub ruin_staking_term: Pubkey,
+    pub minimum_staking_amount: u64,
+    pub maximum_staking_amount: u64,
+    pub start_join_time: i64,
+    pub end_join_time: i64,
+    pub delay_duration: i64,
+    pub apr: u32,
+    pub term_change_delay: i64,
+    pub effective_at: i64,
+    pub post_lock_accrual: PostLockAccrual,
+    pub post_lock_apr: u32,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct TermChangeCancelled {
+    pub ruin_staking: Pubkey,
+    pub ruin_staking_term: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct EmissionConfigured {
+    pub ruin_staking: Pubkey,
+    pub ruin_staking_term: Pubkey,
+    pub reward_per_second: u64,
+    pub emission_end_time: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RewardExchangeRateUpdated {
+    pub ruin_staking: Pubkey,
+    pub ruin_staking_term: Pubkey,
+    pub numerator: u64,
+    pub denominator: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AprUpdated {
+    pub ruin_staking: Pubkey,
+    pub ruin_staking_term: Pubkey,
+    pub apr: u32,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AdminProposed {
+    pub ruin_staking: Pubkey,
+    pub staking_admin: Pubkey,
+    pub pending_admin: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AdminAccepted {
+    pub ruin_staking: Pubkey,
+    pub previous_admin: Pubkey,
+    pub staking_admin: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RolesUpdated {
+    pub ruin_staking: Pubkey,
+    pub pauser: Option<Pubkey>,
+    pub reward_manager: Option<Pubkey>,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PausedChanged {
+    pub ruin_staking: Pubkey,
+    pub paused: bool,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct DistributorFunded {
+    pub ruin_staking: Pubkey,
+    pub ruin_staking_term: Pubkey,
+    pub amount: u64,
+    pub total_funded: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct Staked {
+    pub ruin_staking: Pubkey,
+    pub ruin_staking_term: Pubkey,
+    pub investor: Pubkey,
+    pub amount: u64,
+    pub total_staked: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct Harvested {
+    pub ruin_staking: Pubkey,
+    pub ruin_staking_term: Pubkey,
+    pub investor: Pubkey,
+    pub reward: u128,
+    pub pending_rewards: u128,
+    pub claimable_at: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct Compounded {
+    pub ruin_staking: Pubkey,
+    pub ruin_staking_term: Pubkey,
+    pub investor: Pubkey,
+    pub reward: u128,
+    pub compounded: u64,
+    pub total_staked: u64,
+    pub pending_rewards: u128,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct WithdrawRequested {
+    pub ruin_staking: Pubkey,
+    pub ruin_staking_term: Pubkey,
+    pub investor: Pubkey,
+    pub amount: u64,
+    pub pending_tokens: u64,
+    pub claimable_at: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct WithdrawClaimed {
+    pub ruin_staking: Pubkey,
+    pub ruin_staking_term: Pubkey,
+    pub investor: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RewardClaimed {
+    pub ruin_staking: Pubkey,
+    pub ruin_staking_term: Pubkey,
+    pub investor: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct EarlyWithdrawn {
+    pub ruin_staking: Pubkey,
+    pub ruin_staking_term: Pubkey,
+    pub investor: Pubkey,
+    pub amount: u64,
+    pub principal_penalty: u64,
+    pub reward_penalty: u128,
+    pub penalty_destination: PenaltyDestination,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PenaltiesSwept {
+    pub ruin_staking: Pubkey,
+    pub recipient: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct EmergencyWithdrawn {
+    pub ruin_staking: Pubkey,
+    pub ruin_staking_term: Pubkey,
+    pub investor: Pubkey,
+    pub amount: u64,
+    pub forfeited_rewards: u128,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PositionClosed {
+    pub ruin_staking: Pubkey,
+    pub ruin_staking_term: Pubkey,
+    pub investor: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct DistributorMigrated {
+    pub ruin_staking: Pubkey,
+    pub ruin_staking_term: Pubkey,
+    pub legacy_ruin_staking: Pubkey,
+    pub legacy_distributor: Pubkey,
+    pub distributor: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RewardStreamAdded {
+    pub ruin_staking: Pubkey,
+    pub ruin_staking_term: Pubkey,
+    pub reward_stream: Pubkey,
+    pub stream_index: u8,
+    pub reward_mint: Pubkey,
+    pub distributor: Pubkey,
+    pub reward_mode: RewardMode,
+    pub apr: u32,
+    pub reward_per_second: u64,
+    pub end_time: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RewardStreamFunded {
+    pub ruin_staking_term: Pubkey,
+    pub reward_stream: Pubkey,
+    pub amount: u64,
+    pub total_funded: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RewardStreamJoined {
+    pub ruin_staking_term: Pubkey,
+    pub reward_stream: Pubkey,
+    pub user_reward_stream: Pubkey,
+    pub investor: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct StreamRewardClaimed {
+    pub ruin_staking_term: Pubkey,
+    pub reward_stream: Pubkey,
+    pub investor: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
@@ -1,53 +1,666 @@
 use crate::account::*;
+use crate::error::*;
+use crate::reward;
 use anchor_lang::prelude::*;
 
 pub const ONE_YEAR_IN_SECONDS: u64 = 365 * 24 * 60 * 60;
 pub const DIV_PRECISION: u64 = u64::pow(10, 12);
+pub const VAULT_AUTHORITY_PDA_SEED: &[u8] = b"vault-authority";
+pub const TREASURY_AUTHORITY_PDA_SEED: &[u8] = b"treasury-authority";
+// Shortest notice stakers get before a queued term change applies
+pub const MIN_TERM_CHANGE_DELAY: i64 = 24 * 60 * 60;
 
+// Emission terms need update_emission to run first so the accumulator is current.
+// Fixed APR rewards are converted to the reward mint with the exchange ratio,
+// emission rates are already set in reward tokens.
 pub fn calculate_reward(
-    term: &Account<RuinStakingTerm>,
-    user_staked: &Account<UserStaked>,
-) -> u128 {
-    let clock: Clock = Clock::get().unwrap();
+    term: &RuinStakingTerm,
+    user_staked: &UserStaked,
+    current_time: i64,
+) -> Result<u128> {
+    match term.reward_mode {
+        RewardMode::FixedApr => to_reward_units(
+            term,
+            reward::calculate_reward(
+                user_staked.total_staked,
+                &apr_history(term),
+                reward::effective_post_lock_apr(term.post_lock_accrual, term.post_lock_apr),
+                user_staked.join_time,
+                user_staked.updated_time,
+                current_time,
+                term.lock_duration,
+            )?,
+        ),
+        RewardMode::Emission => reward::emission_reward(
+            user_staked.total_staked,
+            term.acc_reward_per_share,
+            user_staked.reward_debt,
+        ),
+    }
+}
+
+// Reward owed to the staker, taken out of the term's unharvested emission.
+// Call before the staker's reward debt is reset.
+pub fn harvest_reward(
+    term: &mut RuinStakingTerm,
+    user_staked: &UserStaked,
+    current_time: i64,
+) -> Result<u128> {
+    let reward = calculate_reward(term, user_staked, current_time)?;
+
+    if term.reward_mode == RewardMode::Emission {
+        // Harvests add up to what was emitted, saturating keeps the
+        // bookkeeping from ever blocking a payout
+        term.emitted_unharvested = term.emitted_unharvested.saturating_sub(reward);
+    }
+
+    Ok(reward)
+}
+
+pub fn to_reward_units(term: &RuinStakingTerm, reward: u128) -> Result<u128> {
+    reward::to_reward_units(
+        reward,
+        term.reward_rate_numerator,
+        term.reward_rate_denominator,
+    )
+}
+
+pub fn validate_reward_exchange_rate(numerator: u64, denominator: u64) -> Result<()> {
+    if numerator == 0 || denominator == 0 {
+        return Err(ProgramErrorCode::InvalidRewardExchangeRate.into());
+    }
+
+    Ok(())
+}
+
+// Drops the staker's unharvested emission from the term without paying it, so
+// it no longer has to be funded. Never fails, for the emergency path.
+pub fn forfeit_emission(term: &mut RuinStakingTerm, user_staked: &UserStaked) {
+    if term.reward_mode != RewardMode::Emission {
+        return;
+    }
+
+    let forfeited = reward::emission_reward(
+        user_staked.total_staked,
+        term.acc_reward_per_share,
+        user_staked.reward_debt,
+    )
+    .unwrap_or(0);
+    term.emitted_unharvested = term.emitted_unharvested.saturating_sub(forfeited);
+}
+
+// Brings an emission term's accumulator up to `current_time`, capped at the
+// end of the emission. Call before any stake changes on the term.
+pub fn update_emission(term: &mut RuinStakingTerm, current_time: i64) -> Result<()> {
+    if term.reward_mode != RewardMode::Emission {
+        return Ok(());
+    }
+
+    let emission_time = current_time.min(term.emission_end_time);
+    let acc_reward_per_share = reward::emission_per_share(
+        term.reward_per_second,
+        emission_time.saturating_sub(term.last_reward_time),
+        term.total_staked,
+    )?;
+
+    term.acc_reward_per_share = term
+        .acc_reward_per_share
+        .checked_add(acc_reward_per_share)
+        .ok_or_else(|| error!(ProgramErrorCode::MathOverflow))?;
+    term.emitted_unharvested = acc_reward_per_share
+        .checked_mul(term.total_staked)
+        .and_then(|emitted| emitted.checked_add(term.emitted_unharvested))
+        .ok_or_else(|| error!(ProgramErrorCode::MathOverflow))?;
+    term.last_reward_time = term.last_reward_time.max(emission_time);
+
+    Ok(())
+}
+
+// Rewards still to be emitted by an emission term, scaled by DIV_PRECISION.
+pub fn remaining_emission(term: &RuinStakingTerm, current_time: i64) -> Result<u128> {
+    let remaining_time = term
+        .emission_end_time
+        .saturating_sub(current_time.max(term.last_reward_time));
+
+    u128::from(term.reward_per_second)
+        .checked_mul(remaining_time.max(0) as u128)
+        .and_then(|reward| reward.checked_mul(DIV_PRECISION.into()))
+        .ok_or_else(|| error!(ProgramErrorCode::MathOverflow))
+}
+
+// Sets the term APR and records it in the checkpoint ring, overwriting the
+// oldest checkpoint once the ring is full.
+pub fn set_term_apr(term: &mut RuinStakingTerm, apr: u32, current_time: i64) {
+    term.apr = apr;
 
-    let mut start_time = clock.unix_timestamp;
-    let mut end_time = clock.unix_timestamp;
+    let count = usize::from(term.apr_checkpoint_count);
+    let head = usize::from(term.apr_checkpoint_head);
+    let last = (head + APR_CHECKPOINT_CAPACITY - 1) % APR_CHECKPOINT_CAPACITY;
 
-    if user_staked.updated_time > 0 {
-        start_time = user_staked.updated_time;
+    // Several changes in the same second only keep the latest rate
+    if count > 0 && term.apr_checkpoints[last].timestamp == current_time {
+        term.apr_checkpoints[last].apr = apr;
+        return;
     }
 
-    if term.lock_duration > 0
-        && user_staked
-            .join_time
-            .checked_add(term.lock_duration)
+    term.apr_checkpoints[head] = AprCheckpoint {
+        timestamp: current_time,
+        apr,
+    };
+    term.apr_checkpoint_head = ((head + 1) % APR_CHECKPOINT_CAPACITY) as u8;
+    term.apr_checkpoint_count = (count + 1).min(APR_CHECKPOINT_CAPACITY) as u8;
+}
+
+// Sets the APR of a term that may have stakers. A raise grows every
+// outstanding reservation with it and must stay covered by the distributor, a
+// cut keeps the larger reservations until each position is refreshed.
+pub fn change_term_apr(
+    term: &mut RuinStakingTerm,
+    apr: u32,
+    distributor_balance: u64,
+    current_time: i64,
+) -> Result<()> {
+    if apr > term.apr {
+        // Rounded up so the raised reservations never fall short
+        let raise = |value: u128| {
+            value
+                .checked_mul(apr.into())
+                .and_then(|value| value.checked_add(u128::from(term.apr) - 1))
+                .and_then(|value| value.checked_div(term.apr.into()))
+                .ok_or_else(|| error!(ProgramErrorCode::MathOverflow))
+        };
+        let total_reserved_reward = raise(term.total_reserved_reward)?;
+
+        term.reserve_index = raise(term.reserve_index)?;
+        term.reward_liability = term
+            .reward_liability
+            .checked_sub(term.total_reserved_reward)
             .unwrap()
-            < end_time
+            .checked_add(total_reserved_reward)
+            .unwrap();
+        term.total_reserved_reward = total_reserved_reward;
+
+        if term.reward_liability
+            > u128::from(distributor_balance)
+                .checked_mul(DIV_PRECISION.into())
+                .unwrap()
+        {
+            return Err(ProgramErrorCode::InsufficientRewardFunds.into());
+        }
+    }
+
+    set_term_apr(term, apr, current_time);
+
+    Ok(())
+}
+
+// Recorded APR checkpoints from oldest to newest. Terms created before the
+// ring existed fall back to their current APR.
+pub fn apr_history(term: &RuinStakingTerm) -> Vec<AprCheckpoint> {
+    let count = usize::from(term.apr_checkpoint_count);
+    if count == 0 {
+        return vec![AprCheckpoint {
+            timestamp: 0,
+            apr: term.apr,
+        }];
+    }
+
+    let oldest = (usize::from(term.apr_checkpoint_head) + APR_CHECKPOINT_CAPACITY - count)
+        % APR_CHECKPOINT_CAPACITY;
+
+    (0..count)
+        .map(|offset| term.apr_checkpoints[(oldest + offset) % APR_CHECKPOINT_CAPACITY])
+        .collect()
+}
+
+pub fn is_pauser(ruin_staking: &RuinStaking, key: &Pubkey) -> bool {
+    ruin_staking.staking_admin == *key || ruin_staking.pauser == Some(*key)
+}
+
+pub fn is_reward_manager(ruin_staking: &RuinStaking, key: &Pubkey) -> bool {
+    ruin_staking.staking_admin == *key || ruin_staking.reward_manager == Some(*key)
+}
+
+pub fn validate_staking_term(
+    minimum_staking_amount: u64,
+    maximum_staking_amount: u64,
+    start_join_time: i64,
+    end_join_time: i64,
+    apr: u32,
+) -> Result<()> {
+    if apr == 0 {
+        return Err(ProgramErrorCode::InvalidStakingAPR.into());
+    }
+
+    if end_join_time <= start_join_time {
+        return Err(ProgramErrorCode::InvalidJoiningTime.into());
+    }
+
+    if minimum_staking_amount > maximum_staking_amount {
+        return Err(ProgramErrorCode::InvalidStakingAmountRange.into());
+    }
+
+    Ok(())
+}
+
+pub fn validate_term_change_delay(term_change_delay: i64) -> Result<()> {
+    if term_change_delay < MIN_TERM_CHANGE_DELAY {
+        return Err(ProgramErrorCode::InvalidTermChangeDelay.into());
+    }
+
+    Ok(())
+}
+
+// Changes that hurt existing stakers have to go through queue_term_change.
+pub fn validate_instant_term_change(
+    term: &RuinStakingTerm,
+    end_join_time: i64,
+    delay_duration: i64,
+    apr: u32,
+) -> Result<()> {
+    if apr < term.apr
+        || end_join_time < term.end_join_time
+        || delay_duration > term.delay_duration
     {
-        end_time = user_staked.join_time + term.delay_duration;
+        return Err(ProgramErrorCode::TermChangeRequiresTimelock.into());
     }
 
-    let mut stake_time_in_seconds: i64 = end_time.checked_sub(start_time).unwrap();
+    Ok(())
+}
+
+// The post-lock rate is not checkpointed, so a cut would also apply to
+// post-lock time stakers have not harvested yet.
+pub fn validate_instant_post_lock_change(
+    term: &RuinStakingTerm,
+    post_lock_accrual: PostLockAccrual,
+    post_lock_apr: u32,
+) -> Result<()> {
+    let current_rate =
+        reward::effective_post_lock_apr(term.post_lock_accrual, term.post_lock_apr)
+            .unwrap_or(term.apr);
+    let new_rate =
+        reward::effective_post_lock_apr(post_lock_accrual, post_lock_apr).unwrap_or(term.apr);
 
-    if stake_time_in_seconds < 0 {
-        stake_time_in_seconds = 0;
+    if new_rate < current_rate {
+        return Err(ProgramErrorCode::TermChangeRequiresTimelock.into());
     }
 
-    let user_total_staked: u128 = user_staked.total_staked.try_into().unwrap();
+    Ok(())
+}
 
-    let reward: u128 = user_total_staked
-        .checked_mul(stake_time_in_seconds.try_into().unwrap())
-        .unwrap()
-        .checked_mul(term.apr.try_into().unwrap())
-        .unwrap()
-        .checked_div(10000)
-        .unwrap()
-        .checked_mul(DIV_PRECISION.try_into().unwrap())
+pub fn validate_post_lock_accrual(
+    post_lock_accrual: PostLockAccrual,
+    post_lock_apr: u32,
+) -> Result<()> {
+    if post_lock_accrual == PostLockAccrual::PostLockApr && post_lock_apr == 0 {
+        return Err(ProgramErrorCode::InvalidStakingAPR.into());
+    }
+
+    Ok(())
+}
+
+pub fn validate_early_withdraw_config(penalty_bps: u16, reward_penalty_bps: u16) -> Result<()> {
+    if penalty_bps > 10000 || reward_penalty_bps > 10000 {
+        return Err(ProgramErrorCode::InvalidPenaltyBps.into());
+    }
+
+    Ok(())
+}
+
+// Token account early withdraw penalties must be sent to for this term.
+pub fn penalty_destination_address(ruin_staking: &RuinStaking, term: &RuinStakingTerm) -> Pubkey {
+    match term.penalty_destination {
+        PenaltyDestination::Treasury => ruin_staking.treasury,
+        PenaltyDestination::Distributor => term.distributor,
+        PenaltyDestination::FeeAccount => term.penalty_fee_account,
+    }
+}
+
+pub fn apply_bps(amount: u128, bps: u16) -> u128 {
+    amount.checked_mul(bps.into()).unwrap().checked_div(10000).unwrap()
+}
+
+pub fn accrue_pending_reward(
+    term: &mut RuinStakingTerm,
+    user_pending_withdrawl: &mut PendingRewardWithdrawl,
+    pending_reward: u128,
+    current_time: i64,
+) {
+    if pending_reward == 0 {
+        return;
+    }
+
+    user_pending_withdrawl.claimable_at = current_time.checked_add(term.delay_duration).unwrap();
+    user_pending_withdrawl.pending_rewards = user_pending_withdrawl
+        .pending_rewards
+        .checked_add(pending_reward)
+        .unwrap();
+
+    term.total_rewards_accrued = term
+        .total_rewards_accrued
+        .checked_add(pending_reward)
+        .unwrap();
+    term.reward_liability = term.reward_liability.checked_add(pending_reward).unwrap();
+}
+
+// Rewards the user can still earn between now and the end of the lock.
+// Terms without a lock have no end to reserve against and reserve nothing, and
+// post-lock rewards only enter the liability once they are harvested.
+pub fn max_future_reward(
+    term: &RuinStakingTerm,
+    user_staked: &UserStaked,
+    current_time: i64,
+) -> Result<u128> {
+    if term.lock_duration <= 0 || term.reward_mode == RewardMode::Emission {
+        return Ok(0);
+    }
+
+    let lock_end = user_staked.join_time.checked_add(term.lock_duration).unwrap();
+
+    to_reward_units(
+        term,
+        reward::reward_for_duration(
+            user_staked.total_staked,
+            term.apr,
+            lock_end.checked_sub(current_time).unwrap(),
+        )?,
+    )
+}
+
+// User's reservation grown by every APR raise since it was made.
+pub fn current_reserved_reward(term: &RuinStakingTerm, user_staked: &UserStaked) -> Result<u128> {
+    if user_staked.reserved_reward == 0 {
+        return Ok(0);
+    }
+
+    reward::mul_div(
+        user_staked.reserved_reward,
+        term.reserve_index,
+        user_staked.reserve_index,
+    )
+}
+
+// Replaces the user's reservation in the term liability with one matching
+// their current principal and lock, and resets the emission reward debt.
+// Call after every principal or time update.
+pub fn refresh_reward_liability(
+    term: &mut RuinStakingTerm,
+    user_staked: &mut UserStaked,
+    current_time: i64,
+) -> Result<()> {
+    let previous_reward = current_reserved_reward(term, user_staked)?;
+    let reserved_reward = max_future_reward(term, user_staked, current_time)?;
+
+    term.reward_liability = term
+        .reward_liability
+        .checked_sub(previous_reward)
         .unwrap()
-        .checked_div(ONE_YEAR_IN_SECONDS.try_into().unwrap())
+        .checked_add(reserved_reward)
+        .unwrap();
+    term.total_reserved_reward = term
+        .total_reserved_reward
+        .checked_sub(previous_reward)
         .unwrap()
-        .try_into()
+        .checked_add(reserved_reward)
+        .unwrap();
+    user_staked.reserved_reward = reserved_reward;
+    user_staked.reserve_index = term.reserve_index;
+    user_staked.reward_debt = u128::from(user_staked.total_staked)
+        .checked_mul(term.acc_reward_per_share)
+        .ok_or_else(|| error!(ProgramErrorCode::MathOverflow))?;
+
+    Ok(())
+}
+
+// Drops the user's reservation from the term liability, for positions leaving the term.
+pub fn release_reserved_reward(term: &mut RuinStakingTerm, user_staked: &UserStaked) -> Result<()> {
+    let reserved_reward = current_reserved_reward(term, user_staked)?;
+
+    term.reward_liability = term.reward_liability.checked_sub(reserved_reward).unwrap();
+    term.total_reserved_reward = term
+        .total_reserved_reward
+        .checked_sub(reserved_reward)
         .unwrap();
 
-    return reward;
+    Ok(())
+}
+
+// A reward stream and the investor's position in it, loaded from remaining_accounts.
+pub struct RewardStreamPosition<'info> {
+    pub reward_stream: Account<'info, RewardStream>,
+    pub user_reward_stream: Account<'info, UserRewardStream>,
+}
+
+// Loads the investor's position in stream `stream_index` of the term.
+pub fn reward_stream_position<'info>(
+    term: &Pubkey,
+    stream_index: u8,
+    investor: &Pubkey,
+    reward_stream: &AccountInfo<'info>,
+    user_reward_stream: &AccountInfo<'info>,
+) -> Result<RewardStreamPosition<'info>> {
+    let reward_stream: Account<RewardStream> = Account::try_from(reward_stream)?;
+    let user_reward_stream: Account<UserRewardStream> = Account::try_from(user_reward_stream)?;
+
+    if reward_stream.ruin_staking_term != *term
+        || reward_stream.stream_index != stream_index
+        || user_reward_stream.reward_stream != reward_stream.key()
+        || user_reward_stream.investor != *investor
+    {
+        return Err(ProgramErrorCode::InvalidRewardStream.into());
+    }
+
+    // Same seeds as the reward_stream and user_reward_stream contexts
+    let reward_stream_address = Pubkey::create_program_address(
+        &[
+            b"reward_stream",
+            term.as_ref(),
+            &[stream_index],
+            &[reward_stream.reward_stream_bump],
+        ],
+        &crate::ID,
+    );
+    let user_reward_stream_address = Pubkey::create_program_address(
+        &[
+            b"stream_reward",
+            reward_stream.key().as_ref(),
+            investor.as_ref(),
+            &[user_reward_stream.user_reward_stream_bump],
+        ],
+        &crate::ID,
+    );
+    if reward_stream_address != Ok(reward_stream.key())
+        || user_reward_stream_address != Ok(user_reward_stream.key())
+    {
+        return Err(ProgramErrorCode::InvalidRewardStream.into());
+    }
+
+    Ok(RewardStreamPosition {
+        reward_stream,
+        user_reward_stream,
+    })
+}
+
+// Positions in every stream of the term, passed in remaining_accounts as
+// (reward_stream, user_reward_stream) pairs in stream index order. No stream
+// can be skipped since each one assumes the principal only changes when it is
+// settled.
+pub fn reward_stream_positions<'info>(
+    term: &Pubkey,
+    reward_stream_count: u8,
+    investor: &Pubkey,
+    remaining_accounts: &[AccountInfo<'info>],
+) -> Result<Vec<RewardStreamPosition<'info>>> {
+    if remaining_accounts.len() != usize::from(reward_stream_count) * 2 {
+        return Err(ProgramErrorCode::InvalidRewardStream.into());
+    }
+
+    remaining_accounts
+        .chunks(2)
+        .enumerate()
+        .map(|(stream_index, accounts)| {
+            reward_stream_position(
+                term,
+                stream_index as u8,
+                investor,
+                &accounts[0],
+                &accounts[1],
+            )
+        })
+        .collect()
+}
+
+// Brings an emission stream's accumulator up to `current_time`, capped at the
+// end of the stream.
+pub fn update_reward_stream(
+    reward_stream: &mut RewardStream,
+    term_total_staked: u128,
+    current_time: i64,
+) -> Result<()> {
+    if reward_stream.reward_mode != RewardMode::Emission {
+        return Ok(());
+    }
+
+    let emission_time = current_time.min(reward_stream.end_time);
+    let acc_reward_per_share = reward::emission_per_share(
+        reward_stream.reward_per_second,
+        emission_time.saturating_sub(reward_stream.last_reward_time),
+        term_total_staked,
+    )?;
+
+    reward_stream.acc_reward_per_share = reward_stream
+        .acc_reward_per_share
+        .checked_add(acc_reward_per_share)
+        .ok_or_else(|| error!(ProgramErrorCode::MathOverflow))?;
+    reward_stream.last_reward_time = reward_stream.last_reward_time.max(emission_time);
+
+    Ok(())
+}
+
+// Reward earned on a stream by `staked_amount` since the position was last
+// settled, scaled by DIV_PRECISION.
+pub fn calculate_stream_reward(
+    reward_stream: &RewardStream,
+    user_reward_stream: &UserRewardStream,
+    staked_amount: u64,
+    current_time: i64,
+) -> Result<u128> {
+    match reward_stream.reward_mode {
+        RewardMode::FixedApr => reward::reward_for_duration(
+            staked_amount,
+            reward_stream.apr,
+            current_time
+                .min(reward_stream.end_time)
+                .saturating_sub(user_reward_stream.updated_time),
+        ),
+        RewardMode::Emission => reward::emission_reward(
+            staked_amount,
+            reward_stream.acc_reward_per_share,
+            user_reward_stream.reward_debt,
+        ),
+    }
+}
+
+// Settles every stream on `staked_amount` and restarts the positions on
+// `new_staked_amount`. Call before the term's total_staked changes.
+pub fn settle_reward_streams(
+    positions: &mut [RewardStreamPosition],
+    term_total_staked: u128,
+    staked_amount: u64,
+    new_staked_amount: u64,
+    delay_duration: i64,
+    current_time: i64,
+) -> Result<()> {
+    for position in positions.iter_mut() {
+        let reward_stream = &mut position.reward_stream;
+        let user_reward_stream = &mut position.user_reward_stream;
+
+        update_reward_stream(reward_stream, term_total_staked, current_time)?;
+        let reward =
+            calculate_stream_reward(reward_stream, user_reward_stream, staked_amount, current_time)?;
+
+        if reward > 0 {
+            user_reward_stream.pending_rewards = user_reward_stream
+                .pending_rewards
+                .checked_add(reward)
+                .unwrap();
+            user_reward_stream.claimable_at = current_time.checked_add(delay_duration).unwrap();
+            reward_stream.total_rewards_accrued = reward_stream
+                .total_rewards_accrued
+                .checked_add(reward)
+                .unwrap();
+        }
+
+        user_reward_stream.updated_time = current_time;
+        user_reward_stream.reward_debt = u128::from(new_staked_amount)
+            .checked_mul(reward_stream.acc_reward_per_share)
+            .ok_or_else(|| error!(ProgramErrorCode::MathOverflow))?;
+
+        reward_stream.exit(&crate::ID)?;
+        user_reward_stream.exit(&crate::ID)?;
+    }
+
+    Ok(())
+}
+
+// The investor's positions in every stream of the term, passed in
+// remaining_accounts as user_reward_stream accounts in stream index order.
+// The streams themselves are not loaded, so positions can be dropped without
+// touching any reward math.
+pub fn user_reward_streams<'info>(
+    term: &Pubkey,
+    reward_stream_count: u8,
+    investor: &Pubkey,
+    remaining_accounts: &[AccountInfo<'info>],
+) -> Result<Vec<Account<'info, UserRewardStream>>> {
+    if remaining_accounts.len() != usize::from(reward_stream_count) {
+        return Err(ProgramErrorCode::InvalidRewardStream.into());
+    }
+
+    remaining_accounts
+        .iter()
+        .enumerate()
+        .map(|(stream_index, account)| {
+            let user_reward_stream: Account<UserRewardStream> = Account::try_from(account)?;
+            let (reward_stream, _) = Pubkey::find_program_address(
+                &[b"reward_stream", term.as_ref(), &[stream_index as u8]],
+                &crate::ID,
+            );
+            let user_reward_stream_address = Pubkey::create_program_address(
+                &[
+                    b"stream_reward",
+                    reward_stream.as_ref(),
+                    investor.as_ref(),
+                    &[user_reward_stream.user_reward_stream_bump],
+                ],
+                &crate::ID,
+            );
+
+            if user_reward_stream.reward_stream != reward_stream
+                || user_reward_stream.investor != *investor
+                || user_reward_stream_address != Ok(user_reward_stream.key())
+            {
+                return Err(ProgramErrorCode::InvalidRewardStream.into());
+            }
+
+            Ok(user_reward_stream)
+        })
+        .collect()
+}
+
+pub fn validate_reward_stream(
+    reward_mode: RewardMode,
+    apr: u32,
+    end_time: i64,
+    current_time: i64,
+) -> Result<()> {
+    if reward_mode == RewardMode::FixedApr && apr == 0 {
+        return Err(ProgramErrorCode::InvalidStakingAPR.into());
+    }
+
+    if end_time < current_time {
+        return Err(ProgramErrorCode::InvalidEmissionSchedule.into());
+    }
+
+    Ok(())
 }
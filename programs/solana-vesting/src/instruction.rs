@@ -1,25 +1,26 @@
 use crate::account;
 use crate::context;
 use crate::error;
+use crate::event;
 use crate::helpers;
 use crate::{ID};
 
 use account::*;
 use anchor_lang::prelude::*;
-use anchor_spl::token::{transfer, Mint, TokenAccount, set_authority};
-use spl_token::instruction::AuthorityType;
+use anchor_lang::AccountsClose;
+use anchor_spl::token::{close_account, transfer, Mint, TokenAccount, Transfer};
 use context::*;
 use error::*;
+use event::*;
 use helpers::*;
 
-const VAULT_AUTHORITY_PDA_SEED: &[u8] = b"vault-authority";
-
 #[program]
 pub mod solana_vesting {
     use super::*;
 
     pub fn initialize(
         ctx: Context<InitializeStakingInfo>,
+        pool_id: u64,
         minimum_staking_amount: u64,
         maximum_staking_amount: u64,
         lock_duration: i64,
@@ -28,150 +29,1531 @@ pub mod solana_vesting {
         delay_duration: i64,
         apr: u32,
         total_slot: u8,
+        term_change_delay: i64,
     ) -> Result<()> {
+        validate_staking_term(
+            minimum_staking_amount,
+            maximum_staking_amount,
+            start_join_time,
+            end_join_time,
+            apr,
+        )?;
+        validate_term_change_delay(term_change_delay)?;
+
         let ruin_staking_admin: &Signer = &ctx.accounts.ruin_staking_admin;
         let ruin_staking_distributor: &Account<TokenAccount> =
             &ctx.accounts.ruin_staking_distributor;
         let ruin_staking_treasury: &Account<TokenAccount> = &ctx.accounts.ruin_staking_treasury;
         let ruin_staking_token: &Account<Mint> = &ctx.accounts.ruin_staking_token;
+        let ruin_staking_reward_token: &Account<Mint> = &ctx.accounts.ruin_staking_reward_token;
 
         let ruin_staking: &mut Account<RuinStaking> = &mut ctx.accounts.ruin_staking;
         let ruin_staking_term: &mut Account<RuinStakingTerm> = &mut ctx.accounts.ruin_staking_term;
 
+        ruin_staking.ruin_staking_bump = *ctx.bumps.get("ruin_staking").unwrap();
+        ruin_staking.pool_id = pool_id;
         ruin_staking.staking_admin = ruin_staking_admin.key();
         ruin_staking.staking_token = ruin_staking_token.key();
-        ruin_staking.distributor = ruin_staking_distributor.key();
         ruin_staking.treasury = ruin_staking_treasury.key();
+        ruin_staking.treasury_authority_bump = *ctx.bumps.get("treasury_authority").unwrap();
+        ruin_staking.vault_authority_bump = *ctx.bumps.get("vault_authority").unwrap();
         ruin_staking.paused = false;
         ruin_staking.total_staked = 0;
+        ruin_staking.term_count = 1;
+
+        ruin_staking_term.ruin_staking = ruin_staking.key();
+        ruin_staking_term.ruin_staking_term_bump = *ctx.bumps.get("ruin_staking_term").unwrap();
+        ruin_staking_term.term_index = 0;
+        ruin_staking_term.distributor = ruin_staking_distributor.key();
+        ruin_staking_term.reward_mint = ruin_staking_reward_token.key();
+        ruin_staking_term.reward_rate_numerator = 1;
+        ruin_staking_term.reward_rate_denominator = 1;
+        ruin_staking_term.maximum_staking_amount = maximum_staking_amount;
+        ruin_staking_term.minimum_staking_amount = minimum_staking_amount;
+        ruin_staking_term.start_join_time = start_join_time;
+        ruin_staking_term.end_join_time = end_join_time;
+        ruin_staking_term.lock_duration = lock_duration;
+        ruin_staking_term.delay_duration = delay_duration;
+        set_term_apr(ruin_staking_term, apr, Clock::get().unwrap().unix_timestamp);
+        ruin_staking_term.reserve_index = DIV_PRECISION.into();
+        ruin_staking_term.post_lock_accrual = PostLockAccrual::Stop;
+        ruin_staking_term.penalty_destination = PenaltyDestination::Treasury;
+        ruin_staking_term.total_slot = total_slot;
+        ruin_staking_term.term_change_delay = term_change_delay;
+
+        ruin_staking_term.distributor_bump = *ctx.bumps.get("ruin_staking_distributor").unwrap();
+
+        emit!(Initialized {
+            ruin_staking: ctx.accounts.ruin_staking.key(),
+            pool_id,
+            ruin_staking_term: ctx.accounts.ruin_staking_term.key(),
+            staking_admin: ctx.accounts.ruin_staking_admin.key(),
+            staking_token: ctx.accounts.ruin_staking_token.key(),
+            reward_mint: ctx.accounts.ruin_staking_reward_token.key(),
+            treasury: ctx.accounts.ruin_staking_treasury.key(),
+            distributor: ctx.accounts.ruin_staking_distributor.key(),
+            timestamp: Clock::get().unwrap().unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn add_staking_term(
+        ctx: Context<AddStakingTerm>,
+        minimum_staking_amount: u64,
+        maximum_staking_amount: u64,
+        lock_duration: i64,
+        start_join_time: i64,
+        end_join_time: i64,
+        delay_duration: i64,
+        apr: u32,
+        total_slot: u8,
+        term_change_delay: i64,
+    ) -> Result<()> {
+        validate_staking_term(
+            minimum_staking_amount,
+            maximum_staking_amount,
+            start_join_time,
+            end_join_time,
+            apr,
+        )?;
+        validate_term_change_delay(term_change_delay)?;
+
+        let ruin_staking_distributor: &Account<TokenAccount> =
+            &ctx.accounts.ruin_staking_distributor;
+        let ruin_staking_reward_token: &Account<Mint> = &ctx.accounts.ruin_staking_reward_token;
+
+        let ruin_staking: &mut Account<RuinStaking> = &mut ctx.accounts.ruin_staking;
+        let ruin_staking_term: &mut Account<RuinStakingTerm> = &mut ctx.accounts.ruin_staking_term;
 
         ruin_staking_term.ruin_staking = ruin_staking.key();
+        ruin_staking_term.ruin_staking_term_bump = *ctx.bumps.get("ruin_staking_term").unwrap();
+        ruin_staking_term.term_index = ruin_staking.term_count;
+        ruin_staking_term.distributor = ruin_staking_distributor.key();
+        ruin_staking_term.reward_mint = ruin_staking_reward_token.key();
+        ruin_staking_term.reward_rate_numerator = 1;
+        ruin_staking_term.reward_rate_denominator = 1;
         ruin_staking_term.maximum_staking_amount = maximum_staking_amount;
         ruin_staking_term.minimum_staking_amount = minimum_staking_amount;
         ruin_staking_term.start_join_time = start_join_time;
         ruin_staking_term.end_join_time = end_join_time;
         ruin_staking_term.lock_duration = lock_duration;
         ruin_staking_term.delay_duration = delay_duration;
-        ruin_staking_term.apr = apr;
+        set_term_apr(ruin_staking_term, apr, Clock::get().unwrap().unix_timestamp);
+        ruin_staking_term.reserve_index = DIV_PRECISION.into();
+        ruin_staking_term.post_lock_accrual = PostLockAccrual::Stop;
+        ruin_staking_term.penalty_destination = PenaltyDestination::Treasury;
         ruin_staking_term.total_slot = total_slot;
+        ruin_staking_term.term_change_delay = term_change_delay;
+        ruin_staking_term.distributor_bump = *ctx.bumps.get("ruin_staking_distributor").unwrap();
+
+        ruin_staking.term_count = ruin_staking
+            .term_count
+            .checked_add(1)
+            .ok_or_else(|| error!(ProgramErrorCode::TermLimitReached))?;
+
+        emit!(StakingTermAdded {
+            ruin_staking: ctx.accounts.ruin_staking.key(),
+            ruin_staking_term: ctx.accounts.ruin_staking_term.key(),
+            term_index: ctx.accounts.ruin_staking_term.term_index,
+            reward_mint: ctx.accounts.ruin_staking_reward_token.key(),
+            distributor: ctx.accounts.ruin_staking_distributor.key(),
+            timestamp: Clock::get().unwrap().unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_paused(ctx: Context<SetPaused>, paused: bool) -> Result<()> {
+        let ruin_staking: &mut Account<RuinStaking> = &mut ctx.accounts.ruin_staking;
+        ruin_staking.paused = paused;
+
+        emit!(PausedChanged {
+            ruin_staking: ruin_staking.key(),
+            paused,
+            timestamp: Clock::get().unwrap().unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn propose_admin(ctx: Context<ProposeAdmin>, new_admin: Pubkey) -> Result<()> {
+        let ruin_staking: &mut Account<RuinStaking> = &mut ctx.accounts.ruin_staking;
+        ruin_staking.pending_admin = Some(new_admin);
+
+        emit!(AdminProposed {
+            ruin_staking: ruin_staking.key(),
+            staking_admin: ruin_staking.staking_admin,
+            pending_admin: new_admin,
+            timestamp: Clock::get().unwrap().unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn accept_admin(ctx: Context<AcceptAdmin>) -> Result<()> {
+        let ruin_staking: &mut Account<RuinStaking> = &mut ctx.accounts.ruin_staking;
+        let previous_admin = ruin_staking.staking_admin;
+
+        ruin_staking.staking_admin = ctx.accounts.pending_admin.key();
+        ruin_staking.pending_admin = None;
+
+        emit!(AdminAccepted {
+            ruin_staking: ruin_staking.key(),
+            previous_admin,
+            staking_admin: ruin_staking.staking_admin,
+            timestamp: Clock::get().unwrap().unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_roles(
+        ctx: Context<SetRoles>,
+        pauser: Option<Pubkey>,
+        reward_manager: Option<Pubkey>,
+    ) -> Result<()> {
+        let ruin_staking: &mut Account<RuinStaking> = &mut ctx.accounts.ruin_staking;
+        ruin_staking.pauser = pauser;
+        ruin_staking.reward_manager = reward_manager;
+
+        emit!(RolesUpdated {
+            ruin_staking: ruin_staking.key(),
+            pauser,
+            reward_manager,
+            timestamp: Clock::get().unwrap().unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn update_apr(ctx: Context<UpdateApr>, apr: u32) -> Result<()> {
+        if apr == 0 {
+            return Err(ProgramErrorCode::InvalidStakingAPR.into());
+        }
+
+        let distributor_balance = ctx.accounts.distributor_token_account.amount;
+        let ruin_staking_term: &mut Account<RuinStakingTerm> = &mut ctx.accounts.ruin_staking_term;
+        validate_instant_term_change(
+            ruin_staking_term,
+            ruin_staking_term.end_join_time,
+            ruin_staking_term.delay_duration,
+            apr,
+        )?;
+
+        change_term_apr(
+            ruin_staking_term,
+            apr,
+            distributor_balance,
+            Clock::get().unwrap().unix_timestamp,
+        )?;
+
+        emit!(AprUpdated {
+            ruin_staking: ruin_staking_term.ruin_staking,
+            ruin_staking_term: ruin_staking_term.key(),
+            apr,
+            timestamp: Clock::get().unwrap().unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_reward_exchange_rate(
+        ctx: Context<SetRewardExchangeRate>,
+        numerator: u64,
+        denominator: u64,
+    ) -> Result<()> {
+        validate_reward_exchange_rate(numerator, denominator)?;
+
+        let ruin_staking_term: &mut Account<RuinStakingTerm> = &mut ctx.accounts.ruin_staking_term;
+
+        // Reserved and accrued rewards are already expressed in the old ratio
+        if ruin_staking_term.total_staked > 0 || ruin_staking_term.reward_liability > 0 {
+            return Err(ProgramErrorCode::RewardExchangeRateLocked.into());
+        }
+
+        ruin_staking_term.reward_rate_numerator = numerator;
+        ruin_staking_term.reward_rate_denominator = denominator;
+
+        emit!(RewardExchangeRateUpdated {
+            ruin_staking: ruin_staking_term.ruin_staking,
+            ruin_staking_term: ruin_staking_term.key(),
+            numerator,
+            denominator,
+            timestamp: Clock::get().unwrap().unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn update_staking_term(
+        ctx: Context<UpdateStakingTerm>,
+        minimum_staking_amount: u64,
+        maximum_staking_amount: u64,
+        start_join_time: i64,
+        end_join_time: i64,
+        delay_duration: i64,
+        apr: u32,
+    ) -> Result<()> {
+        validate_staking_term(
+            minimum_staking_amount,
+            maximum_staking_amount,
+            start_join_time,
+            end_join_time,
+            apr,
+        )?;
 
-        ruin_staking.distributor_bump = *ctx.bumps.get("ruin_staking_distributor").unwrap();
+        let distributor_balance = ctx.accounts.distributor_token_account.amount;
+        let ruin_staking_term: &mut Account<RuinStakingTerm> = &mut ctx.accounts.ruin_staking_term;
+        validate_instant_term_change(ruin_staking_term, end_join_time, delay_duration, apr)?;
 
-        let (vault_authority, _vault_authority_bump) = Pubkey::find_program_address(&[VAULT_AUTHORITY_PDA_SEED], ctx.program_id);
-        
-        set_authority(
-            ctx.accounts.into_set_authority_context(),
-            AuthorityType::AccountOwner,
-            Some(vault_authority),
+        ruin_staking_term.minimum_staking_amount = minimum_staking_amount;
+        ruin_staking_term.maximum_staking_amount = maximum_staking_amount;
+        ruin_staking_term.start_join_time = start_join_time;
+        ruin_staking_term.end_join_time = end_join_time;
+        ruin_staking_term.delay_duration = delay_duration;
+        change_term_apr(
+            ruin_staking_term,
+            apr,
+            distributor_balance,
+            Clock::get().unwrap().unix_timestamp,
         )?;
 
+        emit!(StakingTermUpdated {
+            ruin_staking: ruin_staking_term.ruin_staking,
+            ruin_staking_term: ruin_staking_term.key(),
+            minimum_staking_amount,
+            maximum_staking_amount,
+            start_join_time,
+            end_join_time,
+            delay_duration,
+            apr,
+            timestamp: Clock::get().unwrap().unix_timestamp,
+        });
+
         Ok(())
     }
 
-    pub fn withdraw(ctx: Context<Withdraw>, amount: u64) -> Result<()> {
+    pub fn queue_term_change(
+        ctx: Context<QueueTermChange>,
+        minimum_staking_amount: u64,
+        maximum_staking_amount: u64,
+        start_join_time: i64,
+        end_join_time: i64,
+        delay_duration: i64,
+        apr: u32,
+        term_change_delay: i64,
+        effective_at: i64,
+        post_lock_accrual: PostLockAccrual,
+        post_lock_apr: u32,
+    ) -> Result<()> {
+        validate_staking_term(
+            minimum_staking_amount,
+            maximum_staking_amount,
+            start_join_time,
+            end_join_time,
+            apr,
+        )?;
+        validate_term_change_delay(term_change_delay)?;
+        validate_post_lock_accrual(post_lock_accrual, post_lock_apr)?;
+
+        let clock: Clock = Clock::get().unwrap();
         let ruin_staking_term: &Account<RuinStakingTerm> = &ctx.accounts.ruin_staking_term;
+
+        if effective_at
+            < clock
+                .unix_timestamp
+                .checked_add(ruin_staking_term.term_change_delay)
+                .unwrap()
+        {
+            return Err(ProgramErrorCode::TermChangeTooEarly.into());
+        }
+
+        let pending_term_change: &mut Account<PendingTermChange> =
+            &mut ctx.accounts.pending_term_change;
+
+        pending_term_change.pending_term_change_bump =
+            *ctx.bumps.get("pending_term_change").unwrap();
+        pending_term_change.ruin_staking_term = ruin_staking_term.key();
+        pending_term_change.minimum_staking_amount = minimum_staking_amount;
+        pending_term_change.maximum_staking_amount = maximum_staking_amount;
+        pending_term_change.start_join_time = start_join_time;
+        pending_term_change.end_join_time = end_join_time;
+        pending_term_change.delay_duration = delay_duration;
+        pending_term_change.apr = apr;
+        pending_term_change.term_change_delay = term_change_delay;
+        pending_term_change.effective_at = effective_at;
+        pending_term_change.post_lock_accrual = post_lock_accrual;
+        pending_term_change.post_lock_apr = post_lock_apr;
+
+        emit!(TermChangeQueued {
+            ruin_staking: ruin_staking_term.ruin_staking,
+            ruin_staking_term: ruin_staking_term.key(),
+            minimum_staking_amount,
+            maximum_staking_amount,
+            start_join_time,
+            end_join_time,
+            delay_duration,
+            apr,
+            term_change_delay,
+            effective_at,
+            post_lock_accrual,
+            post_lock_apr,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn execute_term_change(ctx: Context<ExecuteTermChange>) -> Result<()> {
+        let clock: Clock = Clock::get().unwrap();
+        let pending_term_change: &Account<PendingTermChange> = &ctx.accounts.pending_term_change;
+
+        if pending_term_change.effective_at > clock.unix_timestamp {
+            return Err(ProgramErrorCode::TermChangeNotReady.into());
+        }
+
+        let distributor_balance = ctx.accounts.distributor_token_account.amount;
+        let ruin_staking_term: &mut Account<RuinStakingTerm> = &mut ctx.accounts.ruin_staking_term;
+
+        ruin_staking_term.minimum_staking_amount = pending_term_change.minimum_staking_amount;
+        ruin_staking_term.maximum_staking_amount = pending_term_change.maximum_staking_amount;
+        ruin_staking_term.start_join_time = pending_term_change.start_join_time;
+        ruin_staking_term.end_join_time = pending_term_change.end_join_time;
+        ruin_staking_term.delay_duration = pending_term_change.delay_duration;
+        change_term_apr(
+            ruin_staking_term,
+            pending_term_change.apr,
+            distributor_balance,
+            clock.unix_timestamp,
+        )?;
+        ruin_staking_term.term_change_delay = pending_term_change.term_change_delay;
+        ruin_staking_term.post_lock_accrual = pending_term_change.post_lock_accrual;
+        ruin_staking_term.post_lock_apr = pending_term_change.post_lock_apr;
+
+        emit!(StakingTermUpdated {
+            ruin_staking: ruin_staking_term.ruin_staking,
+            ruin_staking_term: ruin_staking_term.key(),
+            minimum_staking_amount: ruin_staking_term.minimum_staking_amount,
+            maximum_staking_amount: ruin_staking_term.maximum_staking_amount,
+            start_join_time: ruin_staking_term.start_join_time,
+            end_join_time: ruin_staking_term.end_join_time,
+            delay_duration: ruin_staking_term.delay_duration,
+            apr: ruin_staking_term.apr,
+            timestamp: clock.unix_timestamp,
+        });
+        emit!(PostLockAccrualUpdated {
+            ruin_staking: ruin_staking_term.ruin_staking,
+            ruin_staking_term: ruin_staking_term.key(),
+            post_lock_accrual: ruin_staking_term.post_lock_accrual,
+            post_lock_apr: ruin_staking_term.post_lock_apr,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn cancel_term_change(ctx: Context<CancelTermChange>) -> Result<()> {
+        emit!(TermChangeCancelled {
+            ruin_staking: ctx.accounts.ruin_staking.key(),
+            ruin_staking_term: ctx.accounts.ruin_staking_term.key(),
+            timestamp: Clock::get().unwrap().unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn configure_emission(
+        ctx: Context<ConfigureEmission>,
+        reward_per_second: u64,
+        emission_end_time: i64,
+    ) -> Result<()> {
+        let clock: Clock = Clock::get().unwrap();
+        let ruin_staking_term: &mut Account<RuinStakingTerm> = &mut ctx.accounts.ruin_staking_term;
+
+        if emission_end_time < clock.unix_timestamp {
+            return Err(ProgramErrorCode::InvalidEmissionSchedule.into());
+        }
+
+        if ruin_staking_term.reward_mode == RewardMode::Emission {
+            update_emission(ruin_staking_term, clock.unix_timestamp)?;
+        } else {
+            // Existing stakes track rewards by time, not by reward debt
+            if ruin_staking_term.total_staked > 0 {
+                return Err(ProgramErrorCode::RewardModeLocked.into());
+            }
+
+            ruin_staking_term.reward_mode = RewardMode::Emission;
+            ruin_staking_term.last_reward_time = clock.unix_timestamp;
+        }
+
+        ruin_staking_term.reward_per_second = reward_per_second;
+        ruin_staking_term.emission_end_time = emission_end_time;
+
+        // The whole remaining budget has to be in the distributor up front,
+        // next to what was already emitted but not yet harvested
+        let distributor_balance: u128 = ctx.accounts.distributor_token_account.amount.into();
+        let remaining_emission = remaining_emission(ruin_staking_term, clock.unix_timestamp)?;
+        if ruin_staking_term
+            .reward_liability
+            .checked_add(ruin_staking_term.emitted_unharvested)
+            .and_then(|reward| reward.checked_add(remaining_emission))
+            .unwrap()
+            > distributor_balance.checked_mul(DIV_PRECISION.into()).unwrap()
+        {
+            return Err(ProgramErrorCode::InsufficientRewardFunds.into());
+        }
+
+        emit!(EmissionConfigured {
+            ruin_staking: ruin_staking_term.ruin_staking,
+            ruin_staking_term: ruin_staking_term.key(),
+            reward_per_second,
+            emission_end_time,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_post_lock_accrual(
+        ctx: Context<SetPostLockAccrual>,
+        post_lock_accrual: PostLockAccrual,
+        post_lock_apr: u32,
+    ) -> Result<()> {
+        validate_post_lock_accrual(post_lock_accrual, post_lock_apr)?;
+
+        let ruin_staking_term: &mut Account<RuinStakingTerm> = &mut ctx.accounts.ruin_staking_term;
+        validate_instant_post_lock_change(ruin_staking_term, post_lock_accrual, post_lock_apr)?;
+
+        ruin_staking_term.post_lock_accrual = post_lock_accrual;
+        ruin_staking_term.post_lock_apr = post_lock_apr;
+
+        emit!(PostLockAccrualUpdated {
+            ruin_staking: ruin_staking_term.ruin_staking,
+            ruin_staking_term: ruin_staking_term.key(),
+            post_lock_accrual,
+            post_lock_apr,
+            timestamp: Clock::get().unwrap().unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_early_withdraw_config(
+        ctx: Context<SetEarlyWithdrawConfig>,
+        enabled: bool,
+        penalty_bps: u16,
+        reward_penalty_bps: u16,
+        penalty_destination: PenaltyDestination,
+    ) -> Result<()> {
+        validate_early_withdraw_config(penalty_bps, reward_penalty_bps)?;
+
+        // Principal penalties can only top up a distributor holding the staking token
+        if penalty_destination == PenaltyDestination::Distributor
+            && ctx.accounts.ruin_staking_term.reward_mint != ctx.accounts.ruin_staking.staking_token
+        {
+            return Err(ProgramErrorCode::RewardMintMismatch.into());
+        }
+
+        let penalty_fee_account = ctx.accounts.penalty_fee_account.key();
+        let ruin_staking_term: &mut Account<RuinStakingTerm> = &mut ctx.accounts.ruin_staking_term;
+
+        ruin_staking_term.early_withdraw_enabled = enabled;
+        ruin_staking_term.early_withdraw_penalty_bps = penalty_bps;
+        ruin_staking_term.early_withdraw_reward_penalty_bps = reward_penalty_bps;
+        ruin_staking_term.penalty_destination = penalty_destination;
+        ruin_staking_term.penalty_fee_account = penalty_fee_account;
+
+        emit!(EarlyWithdrawConfigUpdated {
+            ruin_staking: ruin_staking_term.ruin_staking,
+            ruin_staking_term: ruin_staking_term.key(),
+            enabled,
+            penalty_bps,
+            reward_penalty_bps,
+            penalty_destination,
+            penalty_fee_account,
+            timestamp: Clock::get().unwrap().unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Moves the principal penalties kept in the treasury out, the rest of the
+    // treasury stays backed by stakes.
+    pub fn sweep_penalties(ctx: Context<SweepPenalties>) -> Result<()> {
+        let amount = ctx.accounts.ruin_staking.penalty_collected;
+
+        let ruin_staking_key = ctx.accounts.ruin_staking.key();
+        let treasury_authority_bump = ctx.accounts.ruin_staking.treasury_authority_bump;
+        let authority_seeds = &[
+            TREASURY_AUTHORITY_PDA_SEED,
+            ruin_staking_key.as_ref(),
+            &[treasury_authority_bump],
+        ];
+        let signer = &[&authority_seeds[..]];
+
+        if amount > 0 {
+            transfer(
+                ctx.accounts
+                    .into_transfer_penalties_to_recipient()
+                    .with_signer(signer),
+                amount,
+            )?;
+        }
+
+        ctx.accounts.ruin_staking.penalty_collected = 0;
+
+        emit!(PenaltiesSwept {
+            ruin_staking: ruin_staking_key,
+            recipient: ctx.accounts.recipient_token_account.key(),
+            amount,
+            timestamp: Clock::get().unwrap().unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn fund_distributor(ctx: Context<FundDistributor>, amount: u64) -> Result<()> {
+        transfer(ctx.accounts.into_transfer_token_to_distributor(), amount)?;
+
+        let ruin_staking_term: &mut Account<RuinStakingTerm> = &mut ctx.accounts.ruin_staking_term;
+        ruin_staking_term.total_funded = ruin_staking_term.total_funded.checked_add(amount).unwrap();
+
+        emit!(DistributorFunded {
+            ruin_staking: ruin_staking_term.ruin_staking,
+            ruin_staking_term: ruin_staking_term.key(),
+            amount,
+            total_funded: ruin_staking_term.total_funded,
+            timestamp: Clock::get().unwrap().unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // The legacy distributor balance funds the new term, and the emptied legacy
+    // account is closed to refund its rent to the admin.
+    pub fn migrate_distributor(ctx: Context<MigrateDistributor>) -> Result<()> {
+        let amount = ctx.accounts.legacy_distributor.amount;
+
+        // Legacy distributors are still owned by the program-wide PDA
+        let legacy_vault_authority_bump = *ctx.bumps.get("legacy_vault_authority").unwrap();
+        let authority_seeds = &[VAULT_AUTHORITY_PDA_SEED, &[legacy_vault_authority_bump]];
+        let signer = &[&authority_seeds[..]];
+
+        if amount > 0 {
+            transfer(
+                ctx.accounts.into_transfer_to_distributor().with_signer(signer),
+                amount,
+            )?;
+        }
+
+        close_account(
+            ctx.accounts
+                .into_close_legacy_distributor()
+                .with_signer(signer),
+        )?;
+
+        let legacy_distributor = ctx.accounts.legacy_distributor.key();
+        let ruin_staking_term: &mut Account<RuinStakingTerm> = &mut ctx.accounts.ruin_staking_term;
+        ruin_staking_term.total_funded = ruin_staking_term.total_funded.checked_add(amount).unwrap();
+
+        emit!(DistributorMigrated {
+            ruin_staking: ctx.accounts.ruin_staking.key(),
+            ruin_staking_term: ruin_staking_term.key(),
+            legacy_ruin_staking: ctx.accounts.legacy_ruin_staking.key(),
+            legacy_distributor,
+            distributor: ruin_staking_term.distributor,
+            amount,
+            timestamp: Clock::get().unwrap().unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn add_reward_stream(
+        ctx: Context<AddRewardStream>,
+        reward_mode: RewardMode,
+        apr: u32,
+        reward_per_second: u64,
+        end_time: i64,
+    ) -> Result<()> {
+        let clock: Clock = Clock::get().unwrap();
+        validate_reward_stream(reward_mode, apr, end_time, clock.unix_timestamp)?;
+
+        let ruin_staking_term: &mut Account<RuinStakingTerm> = &mut ctx.accounts.ruin_staking_term;
+        let reward_stream: &mut Account<RewardStream> = &mut ctx.accounts.reward_stream;
+
+        reward_stream.reward_stream_bump = *ctx.bumps.get("reward_stream").unwrap();
+        reward_stream.ruin_staking_term = ruin_staking_term.key();
+        reward_stream.stream_index = ruin_staking_term.reward_stream_count;
+        reward_stream.reward_mint = ctx.accounts.reward_mint.key();
+        reward_stream.distributor_bump = *ctx.bumps.get("reward_stream_distributor").unwrap();
+        reward_stream.distributor = ctx.accounts.reward_stream_distributor.key();
+        reward_stream.reward_mode = reward_mode;
+        reward_stream.apr = apr;
+        reward_stream.reward_per_second = reward_per_second;
+        reward_stream.last_reward_time = clock.unix_timestamp;
+        reward_stream.start_time = clock.unix_timestamp;
+        reward_stream.end_time = end_time;
+
+        ruin_staking_term.reward_stream_count =
+            ruin_staking_term.reward_stream_count.checked_add(1).unwrap();
+
+        emit!(RewardStreamAdded {
+            ruin_staking: ctx.accounts.ruin_staking.key(),
+            ruin_staking_term: ruin_staking_term.key(),
+            reward_stream: reward_stream.key(),
+            stream_index: reward_stream.stream_index,
+            reward_mint: reward_stream.reward_mint,
+            distributor: reward_stream.distributor,
+            reward_mode,
+            apr,
+            reward_per_second,
+            end_time,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn fund_reward_stream(ctx: Context<FundRewardStream>, amount: u64) -> Result<()> {
+        transfer(ctx.accounts.into_transfer_token_to_distributor(), amount)?;
+
+        let reward_stream: &mut Account<RewardStream> = &mut ctx.accounts.reward_stream;
+        reward_stream.total_funded = reward_stream.total_funded.checked_add(amount).unwrap();
+
+        emit!(RewardStreamFunded {
+            ruin_staking_term: reward_stream.ruin_staking_term,
+            reward_stream: reward_stream.key(),
+            amount,
+            total_funded: reward_stream.total_funded,
+            timestamp: Clock::get().unwrap().unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Opens the investor's position in a stream. The principal can only change
+    // once every stream is joined, so a stake that predates the stream has been
+    // constant since the stream started.
+    pub fn join_reward_stream(ctx: Context<JoinRewardStream>) -> Result<()> {
+        let reward_stream: &Account<RewardStream> = &ctx.accounts.reward_stream;
+        let user_reward_stream: &mut Account<UserRewardStream> =
+            &mut ctx.accounts.user_reward_stream;
+
+        user_reward_stream.user_reward_stream_bump = *ctx.bumps.get("user_reward_stream").unwrap();
+        user_reward_stream.reward_stream = reward_stream.key();
+        user_reward_stream.investor = ctx.accounts.investor.key();
+        user_reward_stream.updated_time = reward_stream.start_time;
+
+        emit!(RewardStreamJoined {
+            ruin_staking_term: reward_stream.ruin_staking_term,
+            reward_stream: reward_stream.key(),
+            user_reward_stream: user_reward_stream.key(),
+            investor: user_reward_stream.investor,
+            timestamp: Clock::get().unwrap().unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn withdraw<'info>(
+        ctx: Context<'_, '_, '_, 'info, Withdraw<'info>>,
+        amount: u64,
+    ) -> Result<()> {
+        let ruin_staking_term: &mut Account<RuinStakingTerm> = &mut ctx.accounts.ruin_staking_term;
         let clock: Clock = Clock::get().unwrap();
 
         let user_staked: &mut Account<UserStaked> = &mut ctx.accounts.user_staked;
-        let user_pending_reward: &mut Account<PendingWithdrawl> =
+        let user_pending_withdraw: &mut Account<PendingWithdrawl> =
             &mut ctx.accounts.user_pending_withdraw;
 
-        if user_pending_reward.claimable_at == 0 {
-            user_pending_reward.pending_withdrawl_bump =
-                *ctx.bumps.get("user_pending_reward").unwrap();
+        if user_pending_withdraw.claimable_at == 0 {
+            user_pending_withdraw.pending_withdrawl_bump =
+                *ctx.bumps.get("user_pending_withdraw").unwrap();
         }
 
         if amount > user_staked.total_staked {
             return Err(ProgramErrorCode::ExceedsCurrentStakingAmount.into());
         }
 
+        // Every queued amount waits the full lock, and sharing one unlock time
+        // would either shorten or extend it
+        if user_pending_withdraw.pending_tokens > 0 {
+            return Err(ProgramErrorCode::WithdrawalAlreadyQueued.into());
+        }
+
+        if user_staked
+            .join_time
+            .checked_add(ruin_staking_term.lock_duration)
+            .unwrap()
+            >= clock.unix_timestamp
+        {
+            return Err(ProgramErrorCode::StakingLocked.into());
+        }
+
+        // Settle rewards on the current principal before it is reduced
+        update_emission(ruin_staking_term, clock.unix_timestamp)?;
+        let mut reward_streams = reward_stream_positions(
+            &ruin_staking_term.key(),
+            ruin_staking_term.reward_stream_count,
+            &ctx.accounts.investor.key(),
+            ctx.remaining_accounts,
+        )?;
+        settle_reward_streams(
+            &mut reward_streams,
+            ruin_staking_term.total_staked,
+            user_staked.total_staked,
+            user_staked.total_staked.checked_sub(amount).unwrap(),
+            ruin_staking_term.delay_duration,
+            clock.unix_timestamp,
+        )?;
+        let pending_reward =
+            harvest_reward(ruin_staking_term, user_staked, clock.unix_timestamp)?;
+
+        accrue_pending_reward(
+            ruin_staking_term,
+            &mut ctx.accounts.user_pending_withdrawl,
+            pending_reward,
+            clock.unix_timestamp,
+        );
+
+        user_staked.total_staked = user_staked.total_staked.checked_sub(amount).unwrap();
+        user_staked.updated_time = clock.unix_timestamp;
+        ruin_staking_term.total_staked = ruin_staking_term
+            .total_staked
+            .checked_sub(amount.into())
+            .unwrap();
+        refresh_reward_liability(ruin_staking_term, user_staked, clock.unix_timestamp)?;
+
+        // Free the slot once the investor fully exits the term
+        if amount > 0 && user_staked.total_staked == 0 {
+            ruin_staking_term.active_stakers =
+                ruin_staking_term.active_stakers.checked_sub(1).unwrap();
+        }
+
+        let ruin_staking: &mut Account<RuinStaking> = &mut ctx.accounts.ruin_staking;
+        ruin_staking.total_staked = ruin_staking
+            .total_staked
+            .checked_sub(amount.into())
+            .unwrap();
+
+        user_pending_withdraw.pending_tokens = amount;
+        user_pending_withdraw.claimable_at = clock
+            .unix_timestamp
+            .checked_add(ruin_staking_term.lock_duration)
+            .unwrap();
+
+        emit!(WithdrawRequested {
+            ruin_staking: ruin_staking.key(),
+            ruin_staking_term: ruin_staking_term.key(),
+            investor: ctx.accounts.investor.key(),
+            amount,
+            pending_tokens: user_pending_withdraw.pending_tokens,
+            claimable_at: user_pending_withdraw.claimable_at,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn early_withdraw<'info>(
+        ctx: Context<'_, '_, '_, 'info, EarlyWithdraw<'info>>,
+        amount: u64,
+    ) -> Result<()> {
+        let ruin_staking_term: &mut Account<RuinStakingTerm> = &mut ctx.accounts.ruin_staking_term;
+        let clock: Clock = Clock::get().unwrap();
+
+        let user_staked: &mut Account<UserStaked> = &mut ctx.accounts.user_staked;
+        let user_pending_withdrawl: &mut Account<PendingRewardWithdrawl> =
+            &mut ctx.accounts.user_pending_withdrawl;
+
+        if !ruin_staking_term.early_withdraw_enabled {
+            return Err(ProgramErrorCode::EarlyWithdrawDisabled.into());
+        }
+
+        if amount == 0 || amount > user_staked.total_staked {
+            return Err(ProgramErrorCode::ExceedsCurrentStakingAmount.into());
+        }
+
         if user_staked
             .join_time
             .checked_add(ruin_staking_term.lock_duration)
             .unwrap()
             < clock.unix_timestamp
         {
-            user_pending_reward.pending_tokens = user_pending_reward
-                .pending_tokens
-                .checked_add(amount)
+            return Err(ProgramErrorCode::StakingUnlocked.into());
+        }
+
+        // Settle rewards on the current principal before it is reduced
+        update_emission(ruin_staking_term, clock.unix_timestamp)?;
+        let mut reward_streams = reward_stream_positions(
+            &ruin_staking_term.key(),
+            ruin_staking_term.reward_stream_count,
+            &ctx.accounts.investor.key(),
+            ctx.remaining_accounts,
+        )?;
+        settle_reward_streams(
+            &mut reward_streams,
+            ruin_staking_term.total_staked,
+            user_staked.total_staked,
+            user_staked.total_staked.checked_sub(amount).unwrap(),
+            ruin_staking_term.delay_duration,
+            clock.unix_timestamp,
+        )?;
+        let pending_reward =
+            harvest_reward(ruin_staking_term, user_staked, clock.unix_timestamp)?;
+
+        accrue_pending_reward(
+            ruin_staking_term,
+            user_pending_withdrawl,
+            pending_reward,
+            clock.unix_timestamp,
+        );
+
+        // Forfeit part of the unclaimed rewards earned by the withdrawn principal,
+        // the forfeited tokens stay in the distributor for other stakers
+        let withdrawn_rewards = user_pending_withdrawl
+            .pending_rewards
+            .checked_div(user_staked.total_staked.into())
+            .unwrap()
+            .checked_mul(amount.into())
+            .unwrap();
+        let reward_penalty = apply_bps(
+            withdrawn_rewards,
+            ruin_staking_term.early_withdraw_reward_penalty_bps,
+        );
+
+        user_pending_withdrawl.pending_rewards = user_pending_withdrawl
+            .pending_rewards
+            .checked_sub(reward_penalty)
+            .unwrap();
+        ruin_staking_term.reward_liability = ruin_staking_term
+            .reward_liability
+            .checked_sub(reward_penalty)
+            .unwrap();
+
+        let principal_penalty: u64 =
+            apply_bps(amount.into(), ruin_staking_term.early_withdraw_penalty_bps)
+                .try_into()
                 .unwrap();
-            user_pending_reward.claimable_at = clock
-                .unix_timestamp
-                .checked_add(ruin_staking_term.lock_duration)
+        let returned_amount = amount.checked_sub(principal_penalty).unwrap();
+
+        user_staked.total_staked = user_staked.total_staked.checked_sub(amount).unwrap();
+        user_staked.updated_time = clock.unix_timestamp;
+        ruin_staking_term.total_staked = ruin_staking_term
+            .total_staked
+            .checked_sub(amount.into())
+            .unwrap();
+        refresh_reward_liability(ruin_staking_term, user_staked, clock.unix_timestamp)?;
+
+        // Free the slot once the investor fully exits the term
+        if user_staked.total_staked == 0 {
+            ruin_staking_term.active_stakers =
+                ruin_staking_term.active_stakers.checked_sub(1).unwrap();
+        }
+
+        let penalty_destination = ruin_staking_term.penalty_destination;
+        if penalty_destination == PenaltyDestination::Distributor {
+            ruin_staking_term.total_funded = ruin_staking_term
+                .total_funded
+                .checked_add(principal_penalty)
                 .unwrap();
         }
 
+        let ruin_staking: &mut Account<RuinStaking> = &mut ctx.accounts.ruin_staking;
+        ruin_staking.total_staked = ruin_staking
+            .total_staked
+            .checked_sub(amount.into())
+            .unwrap();
+        if penalty_destination == PenaltyDestination::Treasury {
+            ruin_staking.penalty_collected = ruin_staking
+                .penalty_collected
+                .checked_add(principal_penalty)
+                .unwrap();
+        }
+
+        let ruin_staking_key = ctx.accounts.ruin_staking.key();
+        let treasury_authority_bump = ctx.accounts.ruin_staking.treasury_authority_bump;
+        let authority_seeds = &[
+            TREASURY_AUTHORITY_PDA_SEED,
+            ruin_staking_key.as_ref(),
+            &[treasury_authority_bump],
+        ];
+        let signer = &[&authority_seeds[..]];
+
+        if returned_amount > 0 {
+            transfer(
+                ctx.accounts
+                    .into_transfer_principal_to_investor()
+                    .with_signer(signer),
+                returned_amount,
+            )?;
+        }
+
+        if principal_penalty > 0 && penalty_destination != PenaltyDestination::Treasury {
+            transfer(
+                ctx.accounts.into_transfer_penalty().with_signer(signer),
+                principal_penalty,
+            )?;
+        }
+
+        emit!(EarlyWithdrawn {
+            ruin_staking: ctx.accounts.ruin_staking.key(),
+            ruin_staking_term: ctx.accounts.ruin_staking_term.key(),
+            investor: ctx.accounts.investor.key(),
+            amount,
+            principal_penalty,
+            reward_penalty,
+            penalty_destination,
+            timestamp: clock.unix_timestamp,
+        });
+
         Ok(())
     }
 
-    pub fn harvest(ctx: Context<Harvest>) -> Result<()> {
-        let ruin_staking_term: &Account<RuinStakingTerm> = &ctx.accounts.ruin_staking_term;
+    // Exit path while the pool is paused: returns the whole principal without
+    // touching reward logic and forfeits every unclaimed reward.
+    pub fn emergency_withdraw<'info>(
+        ctx: Context<'_, '_, '_, 'info, EmergencyWithdraw<'info>>,
+    ) -> Result<()> {
+        let ruin_staking_term: &mut Account<RuinStakingTerm> = &mut ctx.accounts.ruin_staking_term;
+        let clock: Clock = Clock::get().unwrap();
+
+        let user_staked: &Account<UserStaked> = &ctx.accounts.user_staked;
+        let user_pending_withdrawl: &Account<PendingRewardWithdrawl> =
+            &ctx.accounts.user_pending_withdrawl;
+
+        let amount = user_staked.total_staked;
+        let forfeited_rewards = user_pending_withdrawl.pending_rewards;
+
+        // No reward math runs here, so a broken accumulator or stream cannot
+        // trap principal. Emission accrued since the last update is left to the
+        // remaining stakers, and stream positions are dropped unsettled.
+        let user_reward_streams = user_reward_streams(
+            &ruin_staking_term.key(),
+            ruin_staking_term.reward_stream_count,
+            &ctx.accounts.investor.key(),
+            ctx.remaining_accounts,
+        )?;
+        for user_reward_stream in user_reward_streams.iter() {
+            user_reward_stream.close(ctx.accounts.investor.to_account_info())?;
+        }
+
+        ruin_staking_term.reward_liability = ruin_staking_term
+            .reward_liability
+            .checked_sub(forfeited_rewards)
+            .unwrap();
+        forfeit_emission(ruin_staking_term, user_staked);
+        release_reserved_reward(ruin_staking_term, user_staked)?;
+        ruin_staking_term.total_staked = ruin_staking_term
+            .total_staked
+            .checked_sub(amount.into())
+            .unwrap();
+
+        if amount > 0 {
+            ruin_staking_term.active_stakers =
+                ruin_staking_term.active_stakers.checked_sub(1).unwrap();
+        }
+
+        let ruin_staking: &mut Account<RuinStaking> = &mut ctx.accounts.ruin_staking;
+        ruin_staking.total_staked = ruin_staking
+            .total_staked
+            .checked_sub(amount.into())
+            .unwrap();
+
+        if amount > 0 {
+            let ruin_staking_key = ctx.accounts.ruin_staking.key();
+            let treasury_authority_bump = ctx.accounts.ruin_staking.treasury_authority_bump;
+            let authority_seeds = &[
+                TREASURY_AUTHORITY_PDA_SEED,
+                ruin_staking_key.as_ref(),
+                &[treasury_authority_bump],
+            ];
+            let signer = &[&authority_seeds[..]];
+
+            transfer(
+                ctx.accounts
+                    .into_transfer_principal_to_investor()
+                    .with_signer(signer),
+                amount,
+            )?;
+        }
+
+        // Principal already queued by withdraw stays claimable
+        let user_pending_withdraw = &ctx.accounts.user_pending_withdraw;
+        if user_pending_withdraw.owner == ctx.program_id && !user_pending_withdraw.data_is_empty() {
+            let user_pending_withdraw: Account<PendingWithdrawl> =
+                Account::try_from(user_pending_withdraw)?;
+
+            if user_pending_withdraw.pending_tokens == 0 {
+                user_pending_withdraw.close(ctx.accounts.investor.to_account_info())?;
+            }
+        }
+
+        emit!(EmergencyWithdrawn {
+            ruin_staking: ctx.accounts.ruin_staking.key(),
+            ruin_staking_term: ctx.accounts.ruin_staking_term.key(),
+            investor: ctx.accounts.investor.key(),
+            amount,
+            forfeited_rewards,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn claim_pending_withdrawl(ctx: Context<ClaimPendingWithdrawl>) -> Result<()> {
+        let clock: Clock = Clock::get().unwrap();
+
+        let user_pending_withdraw: &Account<PendingWithdrawl> =
+            &ctx.accounts.user_pending_withdraw;
+
+        if user_pending_withdraw.pending_tokens == 0 {
+            return Err(ProgramErrorCode::NothingToClaim.into());
+        }
+
+        if user_pending_withdraw.claimable_at > clock.unix_timestamp {
+            return Err(ProgramErrorCode::StakingLocked.into());
+        }
+
+        let pending_tokens = user_pending_withdraw.pending_tokens;
+        let ruin_staking_key = ctx.accounts.ruin_staking.key();
+        let treasury_authority_bump = ctx.accounts.ruin_staking.treasury_authority_bump;
+        let authority_seeds = &[
+            TREASURY_AUTHORITY_PDA_SEED,
+            ruin_staking_key.as_ref(),
+            &[treasury_authority_bump],
+        ];
+        let signer = &[&authority_seeds[..]];
+
+        transfer(
+            ctx.accounts
+                .into_transfer_principal_to_investor()
+                .with_signer(signer),
+            pending_tokens,
+        )?;
+
+        ctx.accounts.user_pending_withdraw.pending_tokens = 0;
+
+        emit!(WithdrawClaimed {
+            ruin_staking: ctx.accounts.ruin_staking.key(),
+            ruin_staking_term: ctx.accounts.ruin_staking_term.key(),
+            investor: ctx.accounts.investor.key(),
+            amount: pending_tokens,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn close_position<'info>(
+        ctx: Context<'_, '_, '_, 'info, ClosePosition<'info>>,
+    ) -> Result<()> {
+        let ruin_staking_term: &mut Account<RuinStakingTerm> = &mut ctx.accounts.ruin_staking_term;
+        let user_staked: &Account<UserStaked> = &ctx.accounts.user_staked;
+        let user_pending_withdrawl: &Account<PendingRewardWithdrawl> =
+            &ctx.accounts.user_pending_withdrawl;
+
+        // Sub-token reward dust can never be claimed and does not keep the position open
+        if user_staked.total_staked > 0
+            || user_pending_withdrawl.pending_rewards >= DIV_PRECISION.into()
+        {
+            return Err(ProgramErrorCode::PositionNotEmpty.into());
+        }
+
+        let user_reward_streams = user_reward_streams(
+            &ruin_staking_term.key(),
+            ruin_staking_term.reward_stream_count,
+            &ctx.accounts.investor.key(),
+            ctx.remaining_accounts,
+        )?;
+        if user_reward_streams
+            .iter()
+            .any(|user_reward_stream| user_reward_stream.pending_rewards >= DIV_PRECISION.into())
+        {
+            return Err(ProgramErrorCode::PositionNotEmpty.into());
+        }
+        for user_reward_stream in user_reward_streams.iter() {
+            user_reward_stream.close(ctx.accounts.investor.to_account_info())?;
+        }
+
+        ruin_staking_term.reward_liability = ruin_staking_term
+            .reward_liability
+            .checked_sub(user_pending_withdrawl.pending_rewards)
+            .unwrap();
+        release_reserved_reward(ruin_staking_term, user_staked)?;
+
+        let user_pending_withdraw = &ctx.accounts.user_pending_withdraw;
+        if user_pending_withdraw.owner == ctx.program_id && !user_pending_withdraw.data_is_empty() {
+            let user_pending_withdraw: Account<PendingWithdrawl> =
+                Account::try_from(user_pending_withdraw)?;
+
+            if user_pending_withdraw.pending_tokens > 0 {
+                return Err(ProgramErrorCode::PositionNotEmpty.into());
+            }
+
+            user_pending_withdraw.close(ctx.accounts.investor.to_account_info())?;
+        }
+
+        emit!(PositionClosed {
+            ruin_staking: ctx.accounts.ruin_staking.key(),
+            ruin_staking_term: ctx.accounts.ruin_staking_term.key(),
+            investor: ctx.accounts.investor.key(),
+            timestamp: Clock::get().unwrap().unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn harvest<'info>(
+        ctx: Context<'_, '_, '_, 'info, Harvest<'info>>,
+    ) -> Result<()> {
+        let ruin_staking_term: &mut Account<RuinStakingTerm> = &mut ctx.accounts.ruin_staking_term;
         let clock: Clock = Clock::get().unwrap();
 
         let user_staked: &mut Account<UserStaked> = &mut ctx.accounts.user_staked;
 
-        let pending_reward = calculate_reward(ruin_staking_term, &user_staked);
+        update_emission(ruin_staking_term, clock.unix_timestamp)?;
+        let mut reward_streams = reward_stream_positions(
+            &ruin_staking_term.key(),
+            ruin_staking_term.reward_stream_count,
+            &ctx.accounts.investor.key(),
+            ctx.remaining_accounts,
+        )?;
+        settle_reward_streams(
+            &mut reward_streams,
+            ruin_staking_term.total_staked,
+            user_staked.total_staked,
+            user_staked.total_staked,
+            ruin_staking_term.delay_duration,
+            clock.unix_timestamp,
+        )?;
+        let pending_reward =
+            harvest_reward(ruin_staking_term, user_staked, clock.unix_timestamp)?;
+
+        accrue_pending_reward(
+            ruin_staking_term,
+            &mut ctx.accounts.user_pending_withdrawl,
+            pending_reward,
+            clock.unix_timestamp,
+        );
+
+        user_staked.updated_time = clock.unix_timestamp;
+        refresh_reward_liability(ruin_staking_term, user_staked, clock.unix_timestamp)?;
+
+        emit!(Harvested {
+            ruin_staking: ctx.accounts.ruin_staking.key(),
+            ruin_staking_term: ruin_staking_term.key(),
+            investor: ctx.accounts.investor.key(),
+            reward: pending_reward,
+            pending_rewards: ctx.accounts.user_pending_withdrawl.pending_rewards,
+            claimable_at: ctx.accounts.user_pending_withdrawl.claimable_at,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn compound<'info>(
+        ctx: Context<'_, '_, '_, 'info, Compound<'info>>,
+    ) -> Result<()> {
+        let ruin_staking_term: &mut Account<RuinStakingTerm> = &mut ctx.accounts.ruin_staking_term;
+        let clock: Clock = Clock::get().unwrap();
 
+        let user_staked: &mut Account<UserStaked> = &mut ctx.accounts.user_staked;
         let user_pending_withdrawl: &mut Account<PendingRewardWithdrawl> =
             &mut ctx.accounts.user_pending_withdrawl;
 
-        if pending_reward > 0 {
-            user_pending_withdrawl.claimable_at = clock
-                .unix_timestamp
-                .checked_add(ruin_staking_term.delay_duration)
-                .unwrap();
-            user_pending_withdrawl.pending_rewards = user_pending_withdrawl
-                .pending_rewards
-                .checked_add(pending_reward)
-                .unwrap();
-        }
+        update_emission(ruin_staking_term, clock.unix_timestamp)?;
+        let reward = harvest_reward(ruin_staking_term, user_staked, clock.unix_timestamp)?;
+
+        // Only whole tokens up to the maximum staking amount are restaked
+        let whole_tokens: u64 = reward
+            .checked_div(DIV_PRECISION.into())
+            .unwrap()
+            .try_into()
+            .unwrap();
+        let staking_room = ruin_staking_term
+            .maximum_staking_amount
+            .saturating_sub(user_staked.total_staked);
+        let compounded = whole_tokens.min(staking_room);
+
+        let mut reward_streams = reward_stream_positions(
+            &ruin_staking_term.key(),
+            ruin_staking_term.reward_stream_count,
+            &ctx.accounts.investor.key(),
+            ctx.remaining_accounts,
+        )?;
+        settle_reward_streams(
+            &mut reward_streams,
+            ruin_staking_term.total_staked,
+            user_staked.total_staked,
+            user_staked.total_staked.checked_add(compounded).unwrap(),
+            ruin_staking_term.delay_duration,
+            clock.unix_timestamp,
+        )?;
+        let compounded_reward = u128::from(compounded)
+            .checked_mul(DIV_PRECISION.into())
+            .unwrap();
 
+        // Only the sub-token remainder stays claimable without resetting the
+        // harvest cooldown, whole tokens above the maximum are harvested
+        let remainder = reward.checked_rem(DIV_PRECISION.into()).unwrap();
+        let harvested_reward = reward
+            .checked_sub(compounded_reward)
+            .unwrap()
+            .checked_sub(remainder)
+            .unwrap();
+        user_pending_withdrawl.pending_rewards = user_pending_withdrawl
+            .pending_rewards
+            .checked_add(remainder)
+            .unwrap();
+
+        ruin_staking_term.total_rewards_accrued = ruin_staking_term
+            .total_rewards_accrued
+            .checked_add(compounded_reward)
+            .unwrap()
+            .checked_add(remainder)
+            .unwrap();
+        ruin_staking_term.total_rewards_paid = ruin_staking_term
+            .total_rewards_paid
+            .checked_add(compounded_reward)
+            .unwrap();
+        ruin_staking_term.reward_liability = ruin_staking_term
+            .reward_liability
+            .checked_add(remainder)
+            .unwrap();
+        accrue_pending_reward(
+            ruin_staking_term,
+            user_pending_withdrawl,
+            harvested_reward,
+            clock.unix_timestamp,
+        );
+
+        user_staked.total_staked = user_staked.total_staked.checked_add(compounded).unwrap();
         user_staked.updated_time = clock.unix_timestamp;
+        ruin_staking_term.total_staked = ruin_staking_term
+            .total_staked
+            .checked_add(compounded.into())
+            .unwrap();
+
+        refresh_reward_liability(ruin_staking_term, user_staked, clock.unix_timestamp)?;
+
+        // Post-lock accrual is not reserved, so the distributor can run short
+        let distributor_balance: u128 = ctx
+            .accounts
+            .distributor_token_account
+            .amount
+            .checked_sub(compounded)
+            .ok_or_else(|| error!(ProgramErrorCode::InsufficientRewardFunds))?
+            .into();
+        if ruin_staking_term.reward_liability
+            > distributor_balance.checked_mul(DIV_PRECISION.into()).unwrap()
+        {
+            return Err(ProgramErrorCode::InsufficientRewardFunds.into());
+        }
+
+        let ruin_staking: &mut Account<RuinStaking> = &mut ctx.accounts.ruin_staking;
+        ruin_staking.total_staked = ruin_staking
+            .total_staked
+            .checked_add(compounded.into())
+            .unwrap();
+
+        if compounded > 0 {
+            let ruin_staking_key = ctx.accounts.ruin_staking.key();
+            let vault_authority_bump = ctx.accounts.ruin_staking.vault_authority_bump;
+            let authority_seeds = &[
+            VAULT_AUTHORITY_PDA_SEED,
+            ruin_staking_key.as_ref(),
+            &[vault_authority_bump],
+        ];
+            let signer = &[&authority_seeds[..]];
+
+            transfer(
+                ctx.accounts
+                    .into_transfer_reward_to_treasury()
+                    .with_signer(signer),
+                compounded,
+            )?;
+        }
+
+        emit!(Compounded {
+            ruin_staking: ctx.accounts.ruin_staking.key(),
+            ruin_staking_term: ctx.accounts.ruin_staking_term.key(),
+            investor: ctx.accounts.investor.key(),
+            reward,
+            compounded,
+            total_staked: ctx.accounts.user_staked.total_staked,
+            pending_rewards: ctx.accounts.user_pending_withdrawl.pending_rewards,
+            timestamp: clock.unix_timestamp,
+        });
 
         Ok(())
     }
 
-    pub fn claim_pending_reward(ctx: Context<ClaimPendingReward>) -> Result<()> {
+    pub fn claim_pending_reward<'info>(
+        ctx: Context<'_, '_, '_, 'info, ClaimPendingReward<'info>>,
+    ) -> Result<()> {
         let clock: Clock = Clock::get().unwrap();
 
         let user_pending_withdrawl: &Account<PendingRewardWithdrawl> =
             &ctx.accounts.user_pending_withdrawl;
 
-        if user_pending_withdrawl.claimable_at > clock.unix_timestamp
+        let ruin_staking_key = ctx.accounts.ruin_staking.key();
+        let vault_authority_bump = ctx.accounts.ruin_staking.vault_authority_bump;
+        let authority_seeds = &[
+            VAULT_AUTHORITY_PDA_SEED,
+            ruin_staking_key.as_ref(),
+            &[vault_authority_bump],
+        ];
+        let signer = &[&authority_seeds[..]];
+
+        if user_pending_withdrawl.claimable_at <= clock.unix_timestamp
             && user_pending_withdrawl.pending_rewards > 0
         {
-            let (_vault_authority, vault_authority_bump) =
-            Pubkey::find_program_address(&[VAULT_AUTHORITY_PDA_SEED], ctx.program_id);
-
-            let authority_seeds = &[VAULT_AUTHORITY_PDA_SEED, &[vault_authority_bump]];
-            let signer = &[&authority_seeds[..]];
+            let claimed_amount: u64 = user_pending_withdrawl
+                .pending_rewards
+                .checked_div(DIV_PRECISION.into())
+                .unwrap()
+                .try_into()
+                .unwrap();
 
             transfer(
                 ctx.accounts
                     .into_transfer_reward_to_investor()
                     .with_signer(signer),
-                user_pending_withdrawl
-                    .pending_rewards
-                    .checked_div(DIV_PRECISION.try_into().unwrap())
-                    .unwrap()
-                    .try_into()
-                    .unwrap(),
+                claimed_amount,
             )?;
 
-            ctx.accounts.user_pending_withdrawl.pending_rewards = 0;
-            msg!(
-                "{:#?} claim reward: {:#?}",
-                ctx.accounts.investor.key(),
-                ctx.accounts.user_pending_withdrawl.pending_rewards
-            )
+            // Keep the sub-token remainder so that no accrued precision is lost
+            let claimed_rewards: u128 = u128::from(claimed_amount)
+                .checked_mul(DIV_PRECISION.into())
+                .unwrap();
+
+            let ruin_staking_term: &mut Account<RuinStakingTerm> = &mut ctx.accounts.ruin_staking_term;
+            ruin_staking_term.total_rewards_paid = ruin_staking_term
+                .total_rewards_paid
+                .checked_add(claimed_rewards)
+                .unwrap();
+            ruin_staking_term.reward_liability = ruin_staking_term
+                .reward_liability
+                .checked_sub(claimed_rewards)
+                .unwrap();
+
+            ctx.accounts.user_pending_withdrawl.pending_rewards = ctx
+                .accounts
+                .user_pending_withdrawl
+                .pending_rewards
+                .checked_sub(claimed_rewards)
+                .unwrap();
+
+            emit!(RewardClaimed {
+                ruin_staking: ctx.accounts.ruin_staking.key(),
+                ruin_staking_term: ctx.accounts.ruin_staking_term.key(),
+                investor: ctx.accounts.investor.key(),
+                amount: claimed_amount,
+                timestamp: clock.unix_timestamp,
+            });
+        }
+
+        // Streams come in remaining_accounts as (reward_stream, user_reward_stream,
+        // distributor, investor token account) groups in stream index order
+        let ruin_staking_term = ctx.accounts.ruin_staking_term.key();
+        let investor = ctx.accounts.investor.key();
+        let reward_stream_count = ctx.accounts.ruin_staking_term.reward_stream_count;
+
+        if ctx.remaining_accounts.len() != usize::from(reward_stream_count) * 4 {
+            return Err(ProgramErrorCode::InvalidRewardStream.into());
+        }
+
+        for (stream_index, accounts) in ctx.remaining_accounts.chunks(4).enumerate() {
+            let RewardStreamPosition {
+                mut reward_stream,
+                mut user_reward_stream,
+            } = reward_stream_position(
+                &ruin_staking_term,
+                stream_index as u8,
+                &investor,
+                &accounts[0],
+                &accounts[1],
+            )?;
+
+            let distributor_token_account: Account<TokenAccount> = Account::try_from(&accounts[2])?;
+            if distributor_token_account.key() != reward_stream.distributor {
+                return Err(ProgramErrorCode::WrongDistributorAddress.into());
+            }
+
+            let investor_token_account: Account<TokenAccount> = Account::try_from(&accounts[3])?;
+            if investor_token_account.owner != investor {
+                return Err(ProgramErrorCode::InvalidTokenAccountOwnership.into());
+            }
+            if investor_token_account.mint != reward_stream.reward_mint {
+                return Err(ProgramErrorCode::UnsupportedRewardToken.into());
+            }
+
+            let claimed_amount: u64 = user_reward_stream
+                .pending_rewards
+                .checked_div(DIV_PRECISION.into())
+                .unwrap()
+                .try_into()
+                .unwrap();
+
+            // An underfunded stream stays pending instead of blocking the other claims
+            if user_reward_stream.claimable_at > clock.unix_timestamp
+                || claimed_amount == 0
+                || claimed_amount > distributor_token_account.amount
+            {
+                continue;
+            }
+
+            transfer(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    Transfer {
+                        from: accounts[2].clone(),
+                        to: accounts[3].clone(),
+                        authority: ctx.accounts.vault_authority.to_account_info(),
+                    },
+                    signer,
+                ),
+                claimed_amount,
+            )?;
+
+            let claimed_rewards: u128 = u128::from(claimed_amount)
+                .checked_mul(DIV_PRECISION.into())
+                .unwrap();
+
+            reward_stream.total_rewards_paid = reward_stream
+                .total_rewards_paid
+                .checked_add(claimed_rewards)
+                .unwrap();
+            user_reward_stream.pending_rewards = user_reward_stream
+                .pending_rewards
+                .checked_sub(claimed_rewards)
+                .unwrap();
+
+            reward_stream.exit(ctx.program_id)?;
+            user_reward_stream.exit(ctx.program_id)?;
+
+            emit!(StreamRewardClaimed {
+                ruin_staking_term,
+                reward_stream: reward_stream.key(),
+                investor,
+                amount: claimed_amount,
+                timestamp: clock.unix_timestamp,
+            });
         }
 
         Ok(())
     }
 
-    pub fn stake(ctx: Context<Stake>, amount: u64) -> Result<()> {
+    pub fn stake<'info>(
+        ctx: Context<'_, '_, '_, 'info, Stake<'info>>,
+        amount: u64,
+    ) -> Result<()> {
         let investor_token_account: &Account<TokenAccount> = &ctx.accounts.investor_token_account;
-        let ruin_staking_term: &Account<RuinStakingTerm> = &ctx.accounts.ruin_staking_term;
+        let ruin_staking_term: &mut Account<RuinStakingTerm> = &mut ctx.accounts.ruin_staking_term;
         let clock: Clock = Clock::get().unwrap();
 
         let current_time = clock.unix_timestamp;
@@ -188,20 +1570,50 @@ pub mod solana_vesting {
                 *ctx.bumps.get("user_pending_withdrawl").unwrap();
         }
 
-        let pending_reward = calculate_reward(ruin_staking_term, &user_staked.clone());
+        // A new participant takes one of the term's slots
+        if user_staked.total_staked == 0 && amount > 0 {
+            if ruin_staking_term.total_slot > 0
+                && ruin_staking_term.active_stakers >= ruin_staking_term.total_slot.into()
+            {
+                return Err(ProgramErrorCode::StakingSlotsFull.into());
+            }
 
-        if pending_reward > 0 {
-            user_pending_withdrawl.claimable_at = clock
-                .unix_timestamp
-                .checked_add(ruin_staking_term.delay_duration)
-                .unwrap();
-            user_pending_withdrawl.pending_rewards = user_pending_withdrawl
-                .pending_rewards
-                .checked_add(pending_reward)
-                .unwrap();
+            ruin_staking_term.active_stakers = ruin_staking_term
+                .active_stakers
+                .checked_add(1)
+                .ok_or_else(|| error!(ProgramErrorCode::MathOverflow))?;
         }
 
+        update_emission(ruin_staking_term, clock.unix_timestamp)?;
+        let mut reward_streams = reward_stream_positions(
+            &ruin_staking_term.key(),
+            ruin_staking_term.reward_stream_count,
+            &ctx.accounts.investor.key(),
+            ctx.remaining_accounts,
+        )?;
+        settle_reward_streams(
+            &mut reward_streams,
+            ruin_staking_term.total_staked,
+            user_staked.total_staked,
+            user_staked.total_staked.checked_add(amount).unwrap(),
+            ruin_staking_term.delay_duration,
+            clock.unix_timestamp,
+        )?;
+        let pending_reward =
+            harvest_reward(ruin_staking_term, user_staked, clock.unix_timestamp)?;
+
+        accrue_pending_reward(
+            ruin_staking_term,
+            user_pending_withdrawl,
+            pending_reward,
+            current_time,
+        );
+
         user_staked.total_staked = user_staked.total_staked.checked_add(amount).unwrap();
+        ruin_staking_term.total_staked = ruin_staking_term
+            .total_staked
+            .checked_add(amount.into())
+            .unwrap();
         user_staked.join_time = current_time;
 
         if investor_token_account.amount < amount {
@@ -224,8 +1636,32 @@ pub mod solana_vesting {
 
         user_staked.updated_time = current_time;
 
+        refresh_reward_liability(ruin_staking_term, user_staked, current_time)?;
+
+        let distributor_balance: u128 = ctx.accounts.distributor_token_account.amount.into();
+        if ruin_staking_term.reward_liability
+            > distributor_balance.checked_mul(DIV_PRECISION.into()).unwrap()
+        {
+            return Err(ProgramErrorCode::InsufficientRewardFunds.into());
+        }
+
+        let ruin_staking: &mut Account<RuinStaking> = &mut ctx.accounts.ruin_staking;
+        ruin_staking.total_staked = ruin_staking
+            .total_staked
+            .checked_add(amount.into())
+            .unwrap();
+
         transfer(ctx.accounts.into_transfer_token_to_treasury(), amount)?;
 
+        emit!(Staked {
+            ruin_staking: ctx.accounts.ruin_staking.key(),
+            ruin_staking_term: ctx.accounts.ruin_staking_term.key(),
+            investor: ctx.accounts.investor.key(),
+            amount,
+            total_staked: ctx.accounts.user_staked.total_staked,
+            timestamp: current_time,
+        });
+
         Ok(())
     }
 }
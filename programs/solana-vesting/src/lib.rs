@@ -1,8 +1,10 @@
 pub mod account;
 pub mod context;
 pub mod error;
+pub mod event;
 pub mod helpers;
 pub mod instruction;
+pub mod reward;
 
 use anchor_lang::prelude::*;
 use context::*;
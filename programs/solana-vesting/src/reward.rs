@@ -0,0 +1,180 @@
+use crate::account::{AprCheckpoint, PostLockAccrual};
+use crate::error::*;
+use crate::helpers::{DIV_PRECISION, ONE_YEAR_IN_SECONDS};
+use anchor_lang::prelude::*;
+
+// Reward for holding `amount` during `duration` seconds at `apr` basis points,
+// scaled by DIV_PRECISION. Non-positive durations earn nothing.
+pub fn reward_for_duration(amount: u64, apr: u32, duration: i64) -> Result<u128> {
+    if duration <= 0 {
+        return Ok(0);
+    }
+
+    u128::from(amount)
+        .checked_mul(duration as u128)
+        .and_then(|reward| reward.checked_mul(apr.into()))
+        .and_then(|reward| reward.checked_div(10000))
+        .and_then(|reward| reward.checked_mul(DIV_PRECISION.into()))
+        .and_then(|reward| reward.checked_div(ONE_YEAR_IN_SECONDS.into()))
+        .ok_or_else(|| error!(ProgramErrorCode::MathOverflow))
+}
+
+// APR applied to time staked after the lock has ended, None when it keeps
+// following the term's APR checkpoints.
+pub fn effective_post_lock_apr(
+    post_lock_accrual: PostLockAccrual,
+    post_lock_apr: u32,
+) -> Option<u32> {
+    match post_lock_accrual {
+        PostLockAccrual::Stop => Some(0),
+        PostLockAccrual::PostLockApr => Some(post_lock_apr),
+        PostLockAccrual::SameApr => None,
+    }
+}
+
+// Reward for holding `amount` from `start_time` to `end_time`, each segment
+// earning the APR of the checkpoint in force. Checkpoints are chronological and
+// the first one also covers any time before it.
+pub fn reward_for_period(
+    amount: u64,
+    apr_checkpoints: &[AprCheckpoint],
+    start_time: i64,
+    end_time: i64,
+) -> Result<u128> {
+    let mut reward: u128 = 0;
+
+    for (index, checkpoint) in apr_checkpoints.iter().enumerate() {
+        let segment_start = if index == 0 {
+            start_time
+        } else {
+            start_time.max(checkpoint.timestamp)
+        };
+        let segment_end = apr_checkpoints
+            .get(index + 1)
+            .map_or(end_time, |next| end_time.min(next.timestamp));
+
+        let segment_duration = segment_end
+            .checked_sub(segment_start)
+            .ok_or_else(|| error!(ProgramErrorCode::MathOverflow))?;
+
+        reward = reward
+            .checked_add(reward_for_duration(
+                amount,
+                checkpoint.apr,
+                segment_duration,
+            )?)
+            .ok_or_else(|| error!(ProgramErrorCode::MathOverflow))?;
+    }
+
+    Ok(reward)
+}
+
+// Reward accrued since the last update of a stake, with every timestamp passed
+// in so that the math does not depend on the Clock sysvar. Time before
+// `join_time + lock_duration` follows the APR checkpoints, time after it earns
+// `post_lock_apr` when set. Terms without a lock follow the checkpoints for the
+// whole period.
+pub fn calculate_reward(
+    amount: u64,
+    apr_checkpoints: &[AprCheckpoint],
+    post_lock_apr: Option<u32>,
+    join_time: i64,
+    updated_time: i64,
+    current_time: i64,
+    lock_duration: i64,
+) -> Result<u128> {
+    let start_time = if updated_time > 0 {
+        updated_time
+    } else {
+        current_time
+    };
+
+    if lock_duration <= 0 {
+        return reward_for_period(amount, apr_checkpoints, start_time, current_time);
+    }
+
+    let lock_end = join_time
+        .checked_add(lock_duration)
+        .ok_or_else(|| error!(ProgramErrorCode::MathOverflow))?;
+
+    let locked_reward = reward_for_period(
+        amount,
+        apr_checkpoints,
+        start_time,
+        current_time.min(lock_end),
+    )?;
+    let unlocked_reward = match post_lock_apr {
+        Some(post_lock_apr) => {
+            let unlocked_time_in_seconds = current_time
+                .checked_sub(start_time.max(lock_end))
+                .ok_or_else(|| error!(ProgramErrorCode::MathOverflow))?;
+
+            reward_for_duration(amount, post_lock_apr, unlocked_time_in_seconds)?
+        }
+        None => reward_for_period(
+            amount,
+            apr_checkpoints,
+            start_time.max(lock_end),
+            current_time,
+        )?,
+    };
+
+    locked_reward
+        .checked_add(unlocked_reward)
+        .ok_or_else(|| error!(ProgramErrorCode::MathOverflow))
+}
+
+// Growth of an emission term's reward per staked token over `duration`
+// seconds, scaled by DIV_PRECISION. Nothing is emitted while nothing is staked.
+pub fn emission_per_share(
+    reward_per_second: u64,
+    duration: i64,
+    total_staked: u128,
+) -> Result<u128> {
+    if duration <= 0 || total_staked == 0 {
+        return Ok(0);
+    }
+
+    u128::from(reward_per_second)
+        .checked_mul(duration as u128)
+        .and_then(|reward| reward.checked_mul(DIV_PRECISION.into()))
+        .and_then(|reward| reward.checked_div(total_staked))
+        .ok_or_else(|| error!(ProgramErrorCode::MathOverflow))
+}
+
+// Emission rewards earned by `amount` since its debt was last recorded,
+// scaled by DIV_PRECISION.
+pub fn emission_reward(amount: u64, acc_reward_per_share: u128, reward_debt: u128) -> Result<u128> {
+    u128::from(amount)
+        .checked_mul(acc_reward_per_share)
+        .and_then(|reward| reward.checked_sub(reward_debt))
+        .ok_or_else(|| error!(ProgramErrorCode::MathOverflow))
+}
+
+// Converts a reward computed in staking token units into reward token units
+// using the term's exchange ratio. Rounds down.
+pub fn to_reward_units(reward: u128, numerator: u64, denominator: u64) -> Result<u128> {
+    reward
+        .checked_mul(numerator.into())
+        .and_then(|reward| reward.checked_div(denominator.into()))
+        .ok_or_else(|| error!(ProgramErrorCode::MathOverflow))
+}
+
+// `value * numerator / denominator` without forming the full product, so that
+// two DIV_PRECISION scaled operands don't overflow. Rounds down.
+pub fn mul_div(value: u128, numerator: u128, denominator: u128) -> Result<u128> {
+    let quotient = value
+        .checked_div(denominator)
+        .ok_or_else(|| error!(ProgramErrorCode::MathOverflow))?;
+    let remainder = value % denominator;
+
+    quotient
+        .checked_mul(numerator)
+        .and_then(|scaled| {
+            remainder
+                .checked_mul(numerator)
+                .map(|remainder| remainder / denominator)
+                .and_then(|remainder| scaled.checked_add(remainder))
+        })
+        .ok_or_else(|| error!(ProgramErrorCode::MathOverflow))
+}
@@ -0,0 +1,515 @@
+// Anchor account initialization needs account reallocation, which the native
+// program-test runtime does not support. Build the program first with
+// `anchor build`, then run the suite against the BPF binary with `cargo test-bpf`.
+#![cfg(feature = "test-bpf")]
+
+mod common;
+
+use anchor_lang::error::ErrorCode;
+use common::*;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signer;
+use solana_vesting::account::*;
+use solana_vesting::error::ProgramErrorCode;
+
+const STAKE_AMOUNT: u64 = 100_000_000;
+
+// Same instruction with one of its accounts swapped for another address
+fn substitute(ix: &Instruction, original: &Pubkey, replacement: &Pubkey) -> Instruction {
+    let mut ix = ix.clone();
+    let meta = ix
+        .accounts
+        .iter_mut()
+        .find(|meta| meta.pubkey == *original)
+        .unwrap();
+    meta.pubkey = *replacement;
+    ix
+}
+
+fn other_pool(env: &TestEnv) -> Pubkey {
+    pool_address(&env.mint.pubkey(), &env.admin.pubkey(), POOL_ID + 1)
+}
+
+#[tokio::test]
+async fn stake_rejects_substituted_accounts() {
+    let mut env = TestEnv::new().await;
+    let investor = env.create_investor(STAKE_AMOUNT).await;
+    let other = env.create_investor(STAKE_AMOUNT).await;
+    let foreign_token_account = env.create_token_account(&other.keypair.pubkey()).await;
+    let ruin_staking_term = env.ruin_staking_term;
+    let cloned_term = env.clone_account(&ruin_staking_term).await;
+
+    let stake_ix = env.stake_ix(&investor, STAKE_AMOUNT);
+
+    let ix = substitute(&stake_ix, &env.ruin_staking_term, &cloned_term);
+    let result = env.process(&[ix], &[&investor.keypair]).await;
+    assert_anchor_error(result, ErrorCode::ConstraintSeeds);
+
+    let ix = substitute(&stake_ix, &investor.token_account, &other.token_account);
+    let result = env.process(&[ix], &[&investor.keypair]).await;
+    assert_program_error(result, ProgramErrorCode::InvalidTokenAccountOwnership);
+
+    let ix = substitute(&stake_ix, &env.treasury.pubkey(), &foreign_token_account);
+    let result = env.process(&[ix], &[&investor.keypair]).await;
+    assert_program_error(result, ProgramErrorCode::WrongTreasuryAddress);
+
+    let ix = substitute(&stake_ix, &env.distributor, &foreign_token_account);
+    let result = env.process(&[ix], &[&investor.keypair]).await;
+    assert_program_error(result, ProgramErrorCode::WrongDistributorAddress);
+
+    env.process(&[stake_ix], &[&investor.keypair]).await.unwrap();
+    assert_eq!(env.token_balance(&env.treasury.pubkey()).await, STAKE_AMOUNT);
+}
+
+#[tokio::test]
+async fn claim_pending_reward_rejects_substituted_accounts() {
+    let mut env = TestEnv::new().await;
+    let investor = env.create_investor(STAKE_AMOUNT).await;
+    let other = env.create_investor(STAKE_AMOUNT).await;
+
+    for staker in [&investor, &other] {
+        let stake_ix = env.stake_ix(staker, STAKE_AMOUNT);
+        env.process(&[stake_ix], &[&staker.keypair]).await.unwrap();
+    }
+
+    env.set_time(START_TIME + 10 * ONE_DAY).await;
+    for staker in [&investor, &other] {
+        let harvest_ix = env.harvest_ix(staker);
+        env.process(&[harvest_ix], &[&staker.keypair]).await.unwrap();
+    }
+
+    env.set_time(START_TIME + 10 * ONE_DAY + DELAY_DURATION).await;
+    let foreign_token_account = env.create_token_account(&other.keypair.pubkey()).await;
+    let ruin_staking_term = env.ruin_staking_term;
+    let cloned_term = env.clone_account(&ruin_staking_term).await;
+    let claim_ix = env.claim_pending_reward_ix(&investor);
+
+    let ix = substitute(&claim_ix, &env.ruin_staking_term, &cloned_term);
+    let result = env.process(&[ix], &[&investor.keypair]).await;
+    assert_anchor_error(result, ErrorCode::ConstraintSeeds);
+
+    let ix = substitute(
+        &claim_ix,
+        &investor.user_pending_withdrawl,
+        &other.user_pending_withdrawl,
+    );
+    let result = env.process(&[ix], &[&investor.keypair]).await;
+    assert_anchor_error(result, ErrorCode::ConstraintSeeds);
+
+    let ix = substitute(&claim_ix, &env.distributor, &foreign_token_account);
+    let result = env.process(&[ix], &[&investor.keypair]).await;
+    assert_program_error(result, ProgramErrorCode::WrongDistributorAddress);
+
+    let ix = substitute(&claim_ix, &investor.token_account, &other.token_account);
+    let result = env.process(&[ix], &[&investor.keypair]).await;
+    assert_program_error(result, ProgramErrorCode::InvalidTokenAccountOwnership);
+
+    env.process(&[claim_ix], &[&investor.keypair]).await.unwrap();
+    assert!(env.token_balance(&investor.token_account).await > 0);
+}
+
+#[tokio::test]
+async fn harvest_and_compound_reject_substituted_accounts() {
+    let mut env = TestEnv::new().await;
+    let investor = env.create_investor(STAKE_AMOUNT).await;
+    let other = env.create_investor(STAKE_AMOUNT).await;
+
+    for staker in [&investor, &other] {
+        let stake_ix = env.stake_ix(staker, STAKE_AMOUNT);
+        env.process(&[stake_ix], &[&staker.keypair]).await.unwrap();
+    }
+
+    env.set_time(START_TIME + 10 * ONE_DAY).await;
+    let foreign_token_account = env.create_token_account(&other.keypair.pubkey()).await;
+    let ruin_staking_term = env.ruin_staking_term;
+    let cloned_term = env.clone_account(&ruin_staking_term).await;
+
+    let harvest_ix = env.harvest_ix(&investor);
+
+    let ix = substitute(&harvest_ix, &env.ruin_staking_term, &cloned_term);
+    let result = env.process(&[ix], &[&investor.keypair]).await;
+    assert_anchor_error(result, ErrorCode::ConstraintSeeds);
+
+    let ix = substitute(&harvest_ix, &investor.user_staked, &other.user_staked);
+    let result = env.process(&[ix], &[&investor.keypair]).await;
+    assert_anchor_error(result, ErrorCode::ConstraintSeeds);
+
+    let ix = substitute(&harvest_ix, &env.treasury.pubkey(), &foreign_token_account);
+    let result = env.process(&[ix], &[&investor.keypair]).await;
+    assert_program_error(result, ProgramErrorCode::WrongTreasuryAddress);
+
+    let compound_ix = env.compound_ix(&investor);
+
+    let ix = substitute(
+        &compound_ix,
+        &env.vault_authority,
+        &vault_authority_address(&other_pool(&env)),
+    );
+    let result = env.process(&[ix], &[&investor.keypair]).await;
+    assert_anchor_error(result, ErrorCode::ConstraintSeeds);
+
+    let ix = substitute(&compound_ix, &env.distributor, &foreign_token_account);
+    let result = env.process(&[ix], &[&investor.keypair]).await;
+    assert_program_error(result, ProgramErrorCode::WrongDistributorAddress);
+
+    let ix = substitute(&compound_ix, &env.treasury.pubkey(), &foreign_token_account);
+    let result = env.process(&[ix], &[&investor.keypair]).await;
+    assert_program_error(result, ProgramErrorCode::WrongTreasuryAddress);
+
+    // Nothing was harvested by the rejected transactions
+    let user_pending_withdrawl: PendingRewardWithdrawl =
+        env.account(&investor.user_pending_withdrawl).await;
+    assert_eq!(user_pending_withdrawl.pending_rewards, 0);
+}
+
+#[tokio::test]
+async fn withdrawals_reject_substituted_accounts() {
+    let mut env = TestEnv::new().await;
+    let investor = env.create_investor(STAKE_AMOUNT).await;
+    let other = env.create_investor(STAKE_AMOUNT).await;
+
+    let stake_ix = env.stake_ix(&investor, STAKE_AMOUNT);
+    env.process(&[stake_ix], &[&investor.keypair]).await.unwrap();
+
+    let withdraw_time = START_TIME + LOCK_DURATION + 1;
+    env.set_time(withdraw_time).await;
+    let foreign_token_account = env.create_token_account(&other.keypair.pubkey()).await;
+
+    let withdraw_ix = env.withdraw_ix(&investor, STAKE_AMOUNT);
+    let ix = substitute(&withdraw_ix, &env.treasury.pubkey(), &foreign_token_account);
+    let result = env.process(&[ix], &[&investor.keypair]).await;
+    assert_program_error(result, ProgramErrorCode::WrongTreasuryAddress);
+
+    env.process(&[withdraw_ix], &[&investor.keypair]).await.unwrap();
+
+    env.set_time(withdraw_time + LOCK_DURATION).await;
+    let claim_ix = env.claim_pending_withdrawl_ix(&investor);
+
+    let ix = substitute(
+        &claim_ix,
+        &env.treasury_authority,
+        &treasury_authority_address(&other_pool(&env)),
+    );
+    let result = env.process(&[ix], &[&investor.keypair]).await;
+    assert_anchor_error(result, ErrorCode::ConstraintSeeds);
+
+    let ix = substitute(&claim_ix, &env.treasury.pubkey(), &foreign_token_account);
+    let result = env.process(&[ix], &[&investor.keypair]).await;
+    assert_program_error(result, ProgramErrorCode::WrongTreasuryAddress);
+
+    let ix = substitute(&claim_ix, &investor.token_account, &other.token_account);
+    let result = env.process(&[ix], &[&investor.keypair]).await;
+    assert_program_error(result, ProgramErrorCode::InvalidTokenAccountOwnership);
+
+    env.process(&[claim_ix], &[&investor.keypair]).await.unwrap();
+    assert_eq!(env.token_balance(&investor.token_account).await, STAKE_AMOUNT);
+}
+
+#[tokio::test]
+async fn admin_contexts_reject_cloned_term() {
+    let mut env = TestEnv::new().await;
+    let admin = clone_keypair(&env.admin);
+    let ruin_staking_term = env.ruin_staking_term;
+    let cloned_term = env.clone_account(&ruin_staking_term).await;
+
+    let update_apr_ix = env.update_apr_ix(&admin.pubkey(), APR * 2);
+    let ix = substitute(&update_apr_ix, &env.ruin_staking_term, &cloned_term);
+    let result = env.process(&[ix], &[&admin]).await;
+    assert_anchor_error(result, ErrorCode::ConstraintSeeds);
+
+    let admin_token_account = env.create_token_account(&admin.pubkey()).await;
+    let fund_ix = env.fund_distributor_ix(&admin_token_account, 0);
+    let ix = substitute(&fund_ix, &env.ruin_staking_term, &cloned_term);
+    let result = env.process(&[ix], &[&admin]).await;
+    assert_anchor_error(result, ErrorCode::ConstraintSeeds);
+
+    let cloned_term: RuinStakingTerm = env.account(&cloned_term).await;
+    assert_eq!(cloned_term.apr, APR);
+}
+
+#[tokio::test]
+async fn reward_streams_reject_cloned_accounts() {
+    let mut env = TestEnv::new().await;
+    let investor = env.create_investor(STAKE_AMOUNT).await;
+    let admin = clone_keypair(&env.admin);
+
+    let add_stream_ix = env.add_reward_stream_ix(
+        0,
+        RewardMode::FixedApr,
+        APR,
+        0,
+        START_TIME + 10 * ONE_DAY,
+    );
+    env.process(&[add_stream_ix], &[&admin]).await.unwrap();
+
+    let join_ix = env.join_reward_stream_ix(0, &investor);
+    env.process(&[join_ix], &[&investor.keypair]).await.unwrap();
+
+    let reward_stream = env.reward_stream(0);
+    let cloned_stream = env.clone_account(&reward_stream).await;
+    let admin_token_account = env.create_token_account(&admin.pubkey()).await;
+    let fund_ix = env.fund_reward_stream_ix(0, &admin_token_account, 0);
+    let ix = substitute(&fund_ix, &reward_stream, &cloned_stream);
+    let result = env.process(&[ix], &[&admin]).await;
+    assert_anchor_error(result, ErrorCode::ConstraintSeeds);
+
+    // A copy of the position still names the right stream and investor
+    let user_reward_stream = env.user_reward_stream(0, &investor);
+    let cloned_position = env.clone_account(&user_reward_stream).await;
+    let mut stake_ix = env.stake_ix(&investor, STAKE_AMOUNT);
+    stake_ix.accounts.extend(env.reward_stream_metas(&investor, 1));
+    let ix = substitute(&stake_ix, &user_reward_stream, &cloned_position);
+    let result = env.process(&[ix], &[&investor.keypair]).await;
+    assert_program_error(result, ProgramErrorCode::InvalidRewardStream);
+
+    env.process(&[stake_ix], &[&investor.keypair]).await.unwrap();
+}
+
+#[tokio::test]
+async fn early_and_emergency_withdraw_reject_substituted_accounts() {
+    let mut env = TestEnv::new().await;
+    let investor = env.create_investor(STAKE_AMOUNT).await;
+    let other = env.create_investor(STAKE_AMOUNT).await;
+    let foreign_token_account = env.create_token_account(&other.keypair.pubkey()).await;
+    let treasury = env.treasury.pubkey();
+
+    let admin = clone_keypair(&env.admin);
+    let fee_account = env.create_token_account(&admin.pubkey()).await;
+    let config_ix =
+        env.set_early_withdraw_config_ix(1000, 0, PenaltyDestination::FeeAccount, &fee_account);
+    env.process(&[config_ix], &[&admin]).await.unwrap();
+
+    for staker in [&investor, &other] {
+        let stake_ix = env.stake_ix(staker, STAKE_AMOUNT);
+        env.process(&[stake_ix], &[&staker.keypair]).await.unwrap();
+    }
+
+    let early_withdraw_ix = env.early_withdraw_ix(&investor, &fee_account, STAKE_AMOUNT / 2);
+    let ix = substitute(&early_withdraw_ix, &fee_account, &foreign_token_account);
+    let result = env.process(&[ix], &[&investor.keypair]).await;
+    assert_program_error(result, ProgramErrorCode::WrongPenaltyAddress);
+
+    // The other half goes through withdraw so that a PendingWithdrawl exists
+    env.process(&[early_withdraw_ix], &[&investor.keypair])
+        .await
+        .unwrap();
+    env.set_time(START_TIME + LOCK_DURATION + 1).await;
+    let withdraw_ix = env.withdraw_ix(&investor, STAKE_AMOUNT / 2);
+    let other_withdraw_ix = env.withdraw_ix(&other, STAKE_AMOUNT / 2);
+    env.process(
+        &[withdraw_ix, other_withdraw_ix],
+        &[&investor.keypair, &other.keypair],
+    )
+    .await
+    .unwrap();
+
+    let pause_ix = env.set_paused_ix(true);
+    env.process(&[pause_ix], &[&admin]).await.unwrap();
+
+    let emergency_ix = env.emergency_withdraw_ix(&investor);
+
+    let ix = substitute(
+        &emergency_ix,
+        &env.treasury_authority,
+        &treasury_authority_address(&other_pool(&env)),
+    );
+    let result = env.process(&[ix], &[&investor.keypair]).await;
+    assert_anchor_error(result, ErrorCode::ConstraintSeeds);
+
+    let ix = substitute(&emergency_ix, &investor.user_staked, &other.user_staked);
+    let result = env.process(&[ix], &[&investor.keypair]).await;
+    assert_anchor_error(result, ErrorCode::ConstraintSeeds);
+
+    let ix = substitute(
+        &emergency_ix,
+        &investor.user_pending_withdrawl,
+        &other.user_pending_withdrawl,
+    );
+    let result = env.process(&[ix], &[&investor.keypair]).await;
+    assert_anchor_error(result, ErrorCode::ConstraintSeeds);
+
+    let ix = substitute(
+        &emergency_ix,
+        &investor.user_pending_withdraw,
+        &other.user_pending_withdraw,
+    );
+    let result = env.process(&[ix], &[&investor.keypair]).await;
+    assert_anchor_error(result, ErrorCode::ConstraintSeeds);
+
+    let ix = substitute(&emergency_ix, &treasury, &foreign_token_account);
+    let result = env.process(&[ix], &[&investor.keypair]).await;
+    assert_program_error(result, ProgramErrorCode::WrongTreasuryAddress);
+
+    let ix = substitute(&emergency_ix, &investor.token_account, &other.token_account);
+    let result = env.process(&[ix], &[&investor.keypair]).await;
+    assert_program_error(result, ProgramErrorCode::InvalidTokenAccountOwnership);
+
+    env.process(&[emergency_ix], &[&investor.keypair])
+        .await
+        .unwrap();
+
+    // The other investor's queued principal was left alone
+    let pending: PendingWithdrawl = env.account(&other.user_pending_withdraw).await;
+    assert_eq!(pending.pending_tokens, STAKE_AMOUNT / 2);
+}
+
+#[tokio::test]
+async fn close_position_rejects_substituted_pending_withdraw() {
+    let mut env = TestEnv::new().await;
+    let investor = env.create_investor(STAKE_AMOUNT).await;
+    let other = env.create_investor(STAKE_AMOUNT).await;
+
+    for staker in [&investor, &other] {
+        let stake_ix = env.stake_ix(staker, STAKE_AMOUNT);
+        env.process(&[stake_ix], &[&staker.keypair]).await.unwrap();
+    }
+
+    let withdraw_time = START_TIME + LOCK_DURATION + 1;
+    env.set_time(withdraw_time).await;
+    for staker in [&investor, &other] {
+        let withdraw_ix = env.withdraw_ix(staker, STAKE_AMOUNT);
+        env.process(&[withdraw_ix], &[&staker.keypair])
+            .await
+            .unwrap();
+    }
+
+    env.set_time(withdraw_time + 2 * LOCK_DURATION).await;
+    let claim_reward_ix = env.claim_pending_reward_ix(&investor);
+    let claim_principal_ix = env.claim_pending_withdrawl_ix(&investor);
+    env.process(&[claim_reward_ix, claim_principal_ix], &[&investor.keypair])
+        .await
+        .unwrap();
+
+    // The other investor's queued principal must not be closed along
+    let close_ix = env.close_position_ix(&investor);
+    let ix = substitute(
+        &close_ix,
+        &investor.user_pending_withdraw,
+        &other.user_pending_withdraw,
+    );
+    let result = env.process(&[ix], &[&investor.keypair]).await;
+    assert_anchor_error(result, ErrorCode::ConstraintSeeds);
+
+    env.process(&[close_ix], &[&investor.keypair]).await.unwrap();
+
+    let pending: PendingWithdrawl = env.account(&other.user_pending_withdraw).await;
+    assert_eq!(pending.pending_tokens, STAKE_AMOUNT);
+}
+
+#[tokio::test]
+async fn term_changes_reject_substituted_pending_change() {
+    let mut env = TestEnv::new().await;
+    let admin = clone_keypair(&env.admin);
+    let pending_term_change = env.pending_term_change();
+    let (other_pending_term_change, _) = Pubkey::find_program_address(
+        &[b"term_change", term_address(&env.ruin_staking, 1).as_ref()],
+        &solana_vesting::ID,
+    );
+
+    // The change account is created before its seeds are checked, so another
+    // term's address already fails on the account signature
+    let queue_ix = env.queue_term_change_ix(APR / 2, TERM_CHANGE_DELAY, START_TIME + ONE_DAY);
+    let ix = substitute(&queue_ix, &pending_term_change, &other_pending_term_change);
+    let result = env.process(&[ix], &[&admin]).await;
+    assert!(result.is_err());
+
+    env.process(&[queue_ix], &[&admin]).await.unwrap();
+    let cloned_change = env.clone_account(&pending_term_change).await;
+
+    env.set_time(START_TIME + ONE_DAY).await;
+    let execute_ix = env.execute_term_change_ix();
+    let ix = substitute(&execute_ix, &pending_term_change, &cloned_change);
+    let result = env.process(&[ix], &[&admin]).await;
+    assert_anchor_error(result, ErrorCode::ConstraintSeeds);
+
+    let cancel_ix = env.cancel_term_change_ix();
+    let ix = substitute(&cancel_ix, &pending_term_change, &cloned_change);
+    let result = env.process(&[ix], &[&admin]).await;
+    assert_anchor_error(result, ErrorCode::ConstraintSeeds);
+
+    let term: RuinStakingTerm = env.ruin_staking_term_account().await;
+    assert_eq!(term.apr, APR);
+
+    env.process(&[cancel_ix], &[&admin]).await.unwrap();
+}
+
+#[tokio::test]
+async fn migrate_distributor_rejects_substituted_legacy_distributor() {
+    let mut env = TestEnv::new().await;
+    let admin = clone_keypair(&env.admin);
+
+    let legacy_pool = env
+        .create_legacy_pool(&admin.pubkey(), LOCK_DURATION, DISTRIBUTOR_FUNDS)
+        .await;
+    let other_pool = env
+        .create_legacy_pool(&admin.pubkey(), LOCK_DURATION + 1, DISTRIBUTOR_FUNDS)
+        .await;
+
+    // Another legacy term's rewards cannot be moved through this term
+    let migrate_ix = env.migrate_distributor_ix(&legacy_pool);
+    let ix = substitute(&migrate_ix, &legacy_pool.distributor, &other_pool.distributor);
+    let result = env.process(&[ix], &[&admin]).await;
+    assert_anchor_error(result, ErrorCode::ConstraintSeeds);
+
+    let distributor = env.distributor;
+    let ix = substitute(&migrate_ix, &legacy_pool.distributor, &distributor);
+    let result = env.process(&[ix], &[&admin]).await;
+    assert_anchor_error(result, ErrorCode::ConstraintSeeds);
+
+    env.process(&[migrate_ix], &[&admin]).await.unwrap();
+    assert_eq!(env.token_balance(&other_pool.distributor).await, DISTRIBUTOR_FUNDS);
+}
+
+#[tokio::test]
+async fn join_reward_stream_rejects_substituted_accounts() {
+    let mut env = TestEnv::new().await;
+    let investor = env.create_investor(STAKE_AMOUNT).await;
+    let other = env.create_investor(STAKE_AMOUNT).await;
+    let admin = clone_keypair(&env.admin);
+
+    let add_stream_ix = env.add_reward_stream_ix(
+        0,
+        RewardMode::FixedApr,
+        APR,
+        0,
+        START_TIME + 10 * ONE_DAY,
+    );
+    env.process(&[add_stream_ix], &[&admin]).await.unwrap();
+
+    let reward_stream = env.reward_stream(0);
+    let cloned_stream = env.clone_account(&reward_stream).await;
+    let ruin_staking_term = env.ruin_staking_term;
+    let cloned_term = env.clone_account(&ruin_staking_term).await;
+    let join_ix = env.join_reward_stream_ix(0, &investor);
+
+    let ix = substitute(&join_ix, &ruin_staking_term, &cloned_term);
+    let result = env.process(&[ix], &[&investor.keypair]).await;
+    assert_anchor_error(result, ErrorCode::ConstraintSeeds);
+
+    // The position is created before its seeds are checked, so a cloned stream
+    // or another investor's position already fails on the position signature
+    let ix = substitute(&join_ix, &reward_stream, &cloned_stream);
+    let result = env.process(&[ix], &[&investor.keypair]).await;
+    assert!(result.is_err());
+
+    let ix = substitute(
+        &join_ix,
+        &env.user_reward_stream(0, &investor),
+        &env.user_reward_stream(0, &other),
+    );
+    let result = env.process(&[ix], &[&investor.keypair]).await;
+    assert!(result.is_err());
+    assert!(env
+        .context
+        .banks_client
+        .get_account(env.user_reward_stream(0, &other))
+        .await
+        .unwrap()
+        .is_none());
+
+    env.process(&[join_ix], &[&investor.keypair]).await.unwrap();
+    let user_reward_stream: UserRewardStream =
+        env.account(&env.user_reward_stream(0, &investor)).await;
+    assert_eq!(user_reward_stream.investor, investor.keypair.pubkey());
+}
@@ -0,0 +1,342 @@
+// Anchor account initialization needs account reallocation, which the native
+// program-test runtime does not support. Build the program first with
+// `anchor build`, then run the suite against the BPF binary with `cargo test-bpf`.
+#![cfg(feature = "test-bpf")]
+
+mod common;
+
+use anchor_lang::error::ErrorCode;
+use common::*;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_vesting::account::*;
+use solana_vesting::error::ProgramErrorCode;
+use solana_vesting::helpers::MIN_TERM_CHANGE_DELAY;
+
+#[tokio::test]
+async fn admin_transfer_requires_acceptance() {
+    let mut env = TestEnv::new().await;
+    let new_admin = Keypair::new();
+
+    let admin = clone_keypair(&env.admin);
+    let propose_ix = env.propose_admin_ix(&new_admin.pubkey());
+    env.process(&[propose_ix], &[&admin]).await.unwrap();
+
+    let ruin_staking: RuinStaking = env.ruin_staking_account().await;
+    assert_eq!(ruin_staking.staking_admin, admin.pubkey());
+    assert_eq!(ruin_staking.pending_admin, Some(new_admin.pubkey()));
+
+    let intruder = Keypair::new();
+    let accept_ix = env.accept_admin_ix(&intruder.pubkey());
+    let result = env.process(&[accept_ix], &[&intruder]).await;
+    assert_program_error(result, ProgramErrorCode::InvalidPendingAdmin);
+
+    let accept_ix = env.accept_admin_ix(&new_admin.pubkey());
+    env.process(&[accept_ix], &[&new_admin]).await.unwrap();
+
+    let ruin_staking: RuinStaking = env.ruin_staking_account().await;
+    assert_eq!(ruin_staking.staking_admin, new_admin.pubkey());
+    assert_eq!(ruin_staking.pending_admin, None);
+
+    // The previous admin lost its permissions
+    let pause_ix = env.set_paused_ix(true);
+    let result = env.process(&[pause_ix], &[&admin]).await;
+    assert_program_error(result, ProgramErrorCode::InvalidPauser);
+}
+
+#[tokio::test]
+async fn only_admin_can_propose_admin() {
+    let mut env = TestEnv::new().await;
+    let intruder = Keypair::new();
+
+    let mut propose_ix = env.propose_admin_ix(&intruder.pubkey());
+    propose_ix.accounts[1].pubkey = intruder.pubkey();
+    let result = env.process(&[propose_ix], &[&intruder]).await;
+
+    assert_program_error(result, ProgramErrorCode::InvalidStakingAdmin);
+}
+
+#[tokio::test]
+async fn pauser_can_only_toggle_paused() {
+    let mut env = TestEnv::new().await;
+    let pauser = Keypair::new();
+
+    let admin = clone_keypair(&env.admin);
+    let roles_ix = env.set_roles_ix(Some(pauser.pubkey()), None);
+    env.process(&[roles_ix], &[&admin]).await.unwrap();
+
+    let pause_ix = env.set_paused_by_ix(&pauser.pubkey(), true);
+    env.process(&[pause_ix], &[&pauser]).await.unwrap();
+
+    let ruin_staking: RuinStaking = env.ruin_staking_account().await;
+    assert!(ruin_staking.paused);
+
+    let apr_ix = env.update_apr_ix(&pauser.pubkey(), APR * 2);
+    let result = env.process(&[apr_ix], &[&pauser]).await;
+    assert_program_error(result, ProgramErrorCode::InvalidRewardManager);
+}
+
+#[tokio::test]
+async fn reward_manager_can_change_apr_but_not_pause() {
+    let mut env = TestEnv::new().await;
+    let reward_manager = Keypair::new();
+
+    let admin = clone_keypair(&env.admin);
+    let roles_ix = env.set_roles_ix(None, Some(reward_manager.pubkey()));
+    env.process(&[roles_ix], &[&admin]).await.unwrap();
+
+    let apr_ix = env.update_apr_ix(&reward_manager.pubkey(), APR * 2);
+    env.process(&[apr_ix], &[&reward_manager]).await.unwrap();
+
+    let term: RuinStakingTerm = env.ruin_staking_term_account().await;
+    assert_eq!(term.apr, APR * 2);
+
+    let pause_ix = env.set_paused_by_ix(&reward_manager.pubkey(), true);
+    let result = env.process(&[pause_ix], &[&reward_manager]).await;
+    assert_program_error(result, ProgramErrorCode::InvalidPauser);
+}
+
+#[tokio::test]
+async fn lowering_apr_requires_timelock() {
+    let mut env = TestEnv::new().await;
+
+    let admin = clone_keypair(&env.admin);
+    let apr_ix = env.update_apr_ix(&admin.pubkey(), APR / 2);
+    let result = env.process(&[apr_ix], &[&admin]).await;
+
+    assert_program_error(result, ProgramErrorCode::TermChangeRequiresTimelock);
+}
+
+#[tokio::test]
+async fn lengthening_reward_delay_requires_timelock() {
+    let mut env = TestEnv::new().await;
+
+    let admin = clone_keypair(&env.admin);
+    let update_ix = env.update_staking_term_ix(DELAY_DURATION + 1, APR);
+    let result = env.process(&[update_ix], &[&admin]).await;
+    assert_program_error(result, ProgramErrorCode::TermChangeRequiresTimelock);
+
+    let update_ix = env.update_staking_term_ix(DELAY_DURATION - 1, APR);
+    env.process(&[update_ix], &[&admin]).await.unwrap();
+
+    let term: RuinStakingTerm = env.ruin_staking_term_account().await;
+    assert_eq!(term.delay_duration, DELAY_DURATION - 1);
+}
+
+#[tokio::test]
+async fn lowering_post_lock_rate_requires_timelock() {
+    let mut env = TestEnv::new().await;
+
+    let admin = clone_keypair(&env.admin);
+    let accrual_ix = env.set_post_lock_accrual_ix(PostLockAccrual::SameApr, 0);
+    env.process(&[accrual_ix], &[&admin]).await.unwrap();
+
+    let accrual_ix = env.set_post_lock_accrual_ix(PostLockAccrual::PostLockApr, APR / 2);
+    let result = env.process(&[accrual_ix], &[&admin]).await;
+    assert_program_error(result, ProgramErrorCode::TermChangeRequiresTimelock);
+
+    let accrual_ix = env.set_post_lock_accrual_ix(PostLockAccrual::Stop, 0);
+    let result = env.process(&[accrual_ix], &[&admin]).await;
+    assert_program_error(result, ProgramErrorCode::TermChangeRequiresTimelock);
+
+    let queue_ix = env.queue_post_lock_change_ix(
+        APR,
+        PostLockAccrual::PostLockApr,
+        APR / 2,
+        TERM_CHANGE_DELAY,
+        START_TIME + TERM_CHANGE_DELAY,
+    );
+    env.process(&[queue_ix], &[&admin]).await.unwrap();
+
+    env.set_time(START_TIME + TERM_CHANGE_DELAY).await;
+    let execute_ix = env.execute_term_change_ix();
+    env.process(&[execute_ix], &[&admin]).await.unwrap();
+
+    let term: RuinStakingTerm = env.ruin_staking_term_account().await;
+    assert_eq!(term.post_lock_accrual, PostLockAccrual::PostLockApr);
+    assert_eq!(term.post_lock_apr, APR / 2);
+}
+
+#[tokio::test]
+async fn queued_term_change_applies_after_delay() {
+    let mut env = TestEnv::new().await;
+    let admin = clone_keypair(&env.admin);
+
+    let queue_ix =
+        env.queue_term_change_ix(APR / 2, 2 * ONE_DAY, START_TIME + TERM_CHANGE_DELAY - 1);
+    let result = env.process(&[queue_ix], &[&admin]).await;
+    assert_program_error(result, ProgramErrorCode::TermChangeTooEarly);
+
+    let queue_ix = env.queue_term_change_ix(APR / 2, 2 * ONE_DAY, START_TIME + TERM_CHANGE_DELAY);
+    env.process(&[queue_ix], &[&admin]).await.unwrap();
+
+    let execute_ix = env.execute_term_change_ix();
+    let result = env.process(&[execute_ix], &[&admin]).await;
+    assert_program_error(result, ProgramErrorCode::TermChangeNotReady);
+
+    env.set_time(START_TIME + ONE_DAY).await;
+    let execute_ix = env.execute_term_change_ix();
+    env.process(&[execute_ix], &[&admin]).await.unwrap();
+
+    let term: RuinStakingTerm = env.ruin_staking_term_account().await;
+    assert_eq!(term.apr, APR / 2);
+    assert_eq!(term.term_change_delay, 2 * ONE_DAY);
+}
+
+#[tokio::test]
+async fn term_change_delay_has_a_floor() {
+    let mut env = TestEnv::new().await;
+    let admin = clone_keypair(&env.admin);
+
+    let term: RuinStakingTerm = env.ruin_staking_term_account().await;
+    assert_eq!(term.term_change_delay, TERM_CHANGE_DELAY);
+
+    let queue_ix = env.queue_term_change_ix(APR, 0, START_TIME + TERM_CHANGE_DELAY);
+    let result = env.process(&[queue_ix], &[&admin]).await;
+    assert_program_error(result, ProgramErrorCode::InvalidTermChangeDelay);
+
+    let term = term_address(&env.ruin_staking, 1);
+    let add_term_ix =
+        env.add_staking_term_with_delay_ix(&term, LOCK_DURATION, MIN_TERM_CHANGE_DELAY - 1);
+    let result = env.process(&[add_term_ix], &[&admin]).await;
+    assert_program_error(result, ProgramErrorCode::InvalidTermChangeDelay);
+}
+
+#[tokio::test]
+async fn cancelled_term_change_is_discarded() {
+    let mut env = TestEnv::new().await;
+    let admin = clone_keypair(&env.admin);
+
+    let queue_ix = env.queue_term_change_ix(APR / 2, TERM_CHANGE_DELAY, START_TIME + ONE_DAY);
+    let cancel_ix = env.cancel_term_change_ix();
+    env.process(&[queue_ix, cancel_ix], &[&admin])
+        .await
+        .unwrap();
+
+    let pending_term_change = env.pending_term_change();
+    assert!(env
+        .context
+        .banks_client
+        .get_account(pending_term_change)
+        .await
+        .unwrap()
+        .is_none());
+
+    let term: RuinStakingTerm = env.ruin_staking_term_account().await;
+    assert_eq!(term.apr, APR);
+}
+
+#[tokio::test]
+async fn terms_with_lock_durations_equal_modulo_256_get_distinct_distributors() {
+    let mut env = TestEnv::new().await;
+    let term = term_address(&env.ruin_staking, 1);
+
+    let admin = clone_keypair(&env.admin);
+    let add_term_ix = env.add_staking_term_ix(&term, LOCK_DURATION + 256);
+    env.process(&[add_term_ix], &[&admin]).await.unwrap();
+
+    let added_term: RuinStakingTerm = env.account(&term).await;
+    assert_eq!(added_term.distributor, term_distributor(&term));
+    assert_ne!(added_term.distributor, env.distributor);
+}
+
+#[tokio::test]
+async fn migrate_distributor_moves_legacy_rewards_to_term() {
+    let mut env = TestEnv::new().await;
+    let admin = clone_keypair(&env.admin);
+
+    // Rewards of another admin's legacy pool cannot be moved
+    let other_pool = env
+        .create_legacy_pool(&Pubkey::new_unique(), LOCK_DURATION, DISTRIBUTOR_FUNDS)
+        .await;
+    let migrate_ix = env.migrate_distributor_ix(&other_pool);
+    let result = env.process(&[migrate_ix], &[&admin]).await;
+    assert_program_error(result, ProgramErrorCode::InvalidStakingAdmin);
+
+    let legacy_pool = env
+        .create_legacy_pool(&admin.pubkey(), LOCK_DURATION, DISTRIBUTOR_FUNDS)
+        .await;
+    let migrate_ix = env.migrate_distributor_ix(&legacy_pool);
+    env.process(&[migrate_ix], &[&admin]).await.unwrap();
+
+    let distributor = env.distributor;
+    assert_eq!(env.token_balance(&distributor).await, 2 * DISTRIBUTOR_FUNDS);
+    let term: RuinStakingTerm = env.ruin_staking_term_account().await;
+    assert_eq!(term.total_funded, 2 * DISTRIBUTOR_FUNDS);
+    assert!(env
+        .context
+        .banks_client
+        .get_account(legacy_pool.distributor)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[tokio::test]
+async fn migrate_distributor_rejects_current_layouts() {
+    let mut env = TestEnv::new().await;
+    let admin = clone_keypair(&env.admin);
+
+    // A pool with the current layout shares the legacy discriminator
+    let legacy_pool = LegacyPool {
+        ruin_staking: env.ruin_staking,
+        ruin_staking_term: env.ruin_staking_term,
+        distributor: env.distributor,
+    };
+    let migrate_ix = env.migrate_distributor_ix(&legacy_pool);
+    let result = env.process(&[migrate_ix], &[&admin]).await;
+    assert_anchor_error(result, ErrorCode::AccountDiscriminatorMismatch);
+}
+
+#[tokio::test]
+async fn pool_and_terms_live_at_seeded_addresses() {
+    let mut env = TestEnv::new().await;
+
+    let (ruin_staking_address, ruin_staking_bump) = Pubkey::find_program_address(
+        &[
+            b"staking",
+            env.mint.pubkey().as_ref(),
+            env.admin.pubkey().as_ref(),
+            POOL_ID.to_le_bytes().as_ref(),
+        ],
+        &solana_vesting::ID,
+    );
+    assert_eq!(env.ruin_staking, ruin_staking_address);
+
+    let ruin_staking: RuinStaking = env.ruin_staking_account().await;
+    assert_eq!(ruin_staking.ruin_staking_bump, ruin_staking_bump);
+    assert_eq!(ruin_staking.pool_id, POOL_ID);
+
+    let term = term_address(&env.ruin_staking, 1);
+    let admin = clone_keypair(&env.admin);
+    let add_term_ix = env.add_staking_term_ix(&term, LOCK_DURATION);
+    env.process(&[add_term_ix], &[&admin]).await.unwrap();
+
+    let (_, term_bump) = Pubkey::find_program_address(
+        &[b"term", env.ruin_staking.as_ref(), [1u8].as_ref()],
+        &solana_vesting::ID,
+    );
+    let added_term: RuinStakingTerm = env.account(&term).await;
+    assert_eq!(added_term.term_index, 1);
+    assert_eq!(added_term.ruin_staking_term_bump, term_bump);
+
+    // Term addresses are bound to term_count, a term cannot skip an index
+    let skipped_term = term_address(&env.ruin_staking, 3);
+    let add_term_ix = env.add_staking_term_ix(&skipped_term, LOCK_DURATION);
+    assert!(env.process(&[add_term_ix], &[&admin]).await.is_err());
+}
+
+#[tokio::test]
+async fn term_count_stops_at_the_last_index() {
+    let mut env = TestEnv::new().await;
+    let admin = clone_keypair(&env.admin);
+
+    let mut ruin_staking = env.ruin_staking_account().await;
+    ruin_staking.term_count = u8::MAX;
+    env.store_ruin_staking(&ruin_staking).await;
+
+    let term = term_address(&env.ruin_staking, u8::MAX);
+    let add_term_ix = env.add_staking_term_ix(&term, LOCK_DURATION);
+    let result = env.process(&[add_term_ix], &[&admin]).await;
+    assert_program_error(result, ProgramErrorCode::TermLimitReached);
+}
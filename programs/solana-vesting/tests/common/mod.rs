@@ -0,0 +1,1237 @@
+#![allow(dead_code)]
+
+use anchor_lang::{
+    AccountDeserialize, AccountSerialize, AnchorSerialize, Discriminator, InstructionData,
+    ToAccountMetas,
+};
+use assert_matches::assert_matches;
+use solana_program_test::*;
+use solana_sdk::{
+    account::{Account, AccountSharedData},
+    clock::Clock,
+    instruction::{AccountMeta, Instruction, InstructionError},
+    program_pack::Pack,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_instruction, system_program, sysvar,
+    transaction::{Transaction, TransactionError},
+    transport::TransportError,
+};
+use solana_vesting::account::{
+    LegacyRuinStaking, LegacyRuinStakingTerm, PenaltyDestination, PostLockAccrual, RewardMode,
+    RuinStaking, RuinStakingTerm,
+};
+use solana_vesting::error::ProgramErrorCode;
+use solana_vesting::helpers::{TREASURY_AUTHORITY_PDA_SEED, VAULT_AUTHORITY_PDA_SEED};
+use solana_vesting::instruction::{accounts, instruction};
+
+pub const START_TIME: i64 = 1_650_000_000;
+pub const ONE_DAY: i64 = 24 * 60 * 60;
+pub const LOCK_DURATION: i64 = 30 * ONE_DAY;
+pub const DELAY_DURATION: i64 = ONE_DAY;
+pub const TERM_CHANGE_DELAY: i64 = ONE_DAY;
+pub const APR: u32 = 1_000;
+pub const MINIMUM_STAKING_AMOUNT: u64 = 10;
+pub const MAXIMUM_STAKING_AMOUNT: u64 = 1_000_000_000_000_000_000;
+pub const TOTAL_SLOT: u8 = 2;
+pub const DISTRIBUTOR_FUNDS: u64 = 1_000_000_000_000;
+pub const POOL_ID: u64 = 0;
+
+pub struct TestEnv {
+    pub context: ProgramTestContext,
+    pub slot: u64,
+    pub admin: Keypair,
+    pub mint: Keypair,
+    pub ruin_staking: Pubkey,
+    pub ruin_staking_term: Pubkey,
+    pub treasury: Keypair,
+    pub distributor: Pubkey,
+    pub vault_authority: Pubkey,
+    pub treasury_authority: Pubkey,
+}
+
+pub struct LegacyPool {
+    pub ruin_staking: Pubkey,
+    pub ruin_staking_term: Pubkey,
+    pub distributor: Pubkey,
+}
+
+pub struct Investor {
+    pub keypair: Keypair,
+    pub token_account: Pubkey,
+    pub user_staked: Pubkey,
+    pub user_pending_withdrawl: Pubkey,
+    pub user_pending_withdraw: Pubkey,
+}
+
+pub fn program_test() -> ProgramTest {
+    ProgramTest::new(
+        "solana_vesting",
+        solana_vesting::ID,
+        processor!(solana_vesting::instruction::entry),
+    )
+}
+
+pub fn assert_program_error(result: Result<(), TransportError>, error: ProgramErrorCode) {
+    let code: u32 = error.into();
+    assert_matches!(
+        result,
+        Err(TransportError::TransactionError(TransactionError::InstructionError(
+            _,
+            InstructionError::Custom(c)
+        ))) if c == code
+    );
+}
+
+pub fn assert_anchor_error(result: Result<(), TransportError>, error: anchor_lang::error::ErrorCode) {
+    let code: u32 = error.into();
+    assert_matches!(
+        result,
+        Err(TransportError::TransactionError(TransactionError::InstructionError(
+            _,
+            InstructionError::Custom(c)
+        ))) if c == code
+    );
+}
+
+pub fn vault_authority_address(ruin_staking: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[VAULT_AUTHORITY_PDA_SEED, ruin_staking.as_ref()],
+        &solana_vesting::ID,
+    )
+    .0
+}
+
+// Program-wide PDA that owned every distributor before vault authorities were
+// scoped per pool
+pub fn legacy_vault_authority_address() -> Pubkey {
+    Pubkey::find_program_address(&[VAULT_AUTHORITY_PDA_SEED], &solana_vesting::ID).0
+}
+
+fn legacy_account_data<T: AnchorSerialize>(discriminator: [u8; 8], value: &T, len: usize) -> Vec<u8> {
+    let mut data = discriminator.to_vec();
+    data.extend(value.try_to_vec().unwrap());
+    data.resize(len, 0);
+    data
+}
+
+pub fn treasury_authority_address(ruin_staking: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[TREASURY_AUTHORITY_PDA_SEED, ruin_staking.as_ref()],
+        &solana_vesting::ID,
+    )
+    .0
+}
+
+impl TestEnv {
+    pub async fn new() -> Self {
+        let context = program_test().start_with_context().await;
+
+        let admin = Keypair::new();
+        let mint = Keypair::new();
+        let treasury = Keypair::new();
+
+        let ruin_staking = pool_address(&mint.pubkey(), &admin.pubkey(), POOL_ID);
+        let ruin_staking_term = term_address(&ruin_staking, 0);
+
+        let distributor = term_distributor(&ruin_staking_term);
+        let vault_authority = vault_authority_address(&ruin_staking);
+        let treasury_authority = treasury_authority_address(&ruin_staking);
+
+        let mut env = TestEnv {
+            context,
+            slot: 1,
+            admin,
+            mint,
+            ruin_staking,
+            ruin_staking_term,
+            treasury,
+            distributor,
+            vault_authority,
+            treasury_authority,
+        };
+
+        env.set_time(START_TIME).await;
+        env.airdrop(&env.admin.pubkey(), 10_000_000_000).await;
+        env.create_mint().await;
+
+        let initialize_ix = env.initialize_ix();
+        let admin = clone_keypair(&env.admin);
+        let treasury = clone_keypair(&env.treasury);
+        env.process(&[initialize_ix], &[&admin, &treasury])
+            .await
+            .unwrap();
+
+        env.fund_distributor(DISTRIBUTOR_FUNDS).await;
+
+        env
+    }
+
+    // Moves the bank to a new slot, so repeated transactions get a fresh
+    // blockhash, and pins the clock to the given unix timestamp.
+    pub async fn set_time(&mut self, unix_timestamp: i64) {
+        self.slot += 2;
+        self.context.warp_to_slot(self.slot).unwrap();
+
+        let mut clock: Clock = self.context.banks_client.get_sysvar().await.unwrap();
+        clock.unix_timestamp = unix_timestamp;
+        self.context.set_sysvar(&clock);
+    }
+
+    pub async fn process(
+        &mut self,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Result<(), TransportError> {
+        let blockhash = self
+            .context
+            .banks_client
+            .get_latest_blockhash()
+            .await
+            .unwrap();
+
+        let mut all_signers: Vec<&Keypair> = vec![&self.context.payer];
+        all_signers.extend_from_slice(signers);
+
+        let transaction = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&self.context.payer.pubkey()),
+            &all_signers,
+            blockhash,
+        );
+
+        self.context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+    }
+
+    pub async fn airdrop(&mut self, to: &Pubkey, lamports: u64) {
+        let ix = system_instruction::transfer(&self.context.payer.pubkey(), to, lamports);
+        self.process(&[ix], &[]).await.unwrap();
+    }
+
+    async fn create_mint(&mut self) {
+        let rent = self.context.banks_client.get_rent().await.unwrap();
+        let mint = clone_keypair(&self.mint);
+
+        let ixs = [
+            system_instruction::create_account(
+                &self.context.payer.pubkey(),
+                &mint.pubkey(),
+                rent.minimum_balance(spl_token::state::Mint::LEN),
+                spl_token::state::Mint::LEN as u64,
+                &spl_token::id(),
+            ),
+            spl_token::instruction::initialize_mint(
+                &spl_token::id(),
+                &mint.pubkey(),
+                &self.admin.pubkey(),
+                None,
+                6,
+            )
+            .unwrap(),
+        ];
+
+        self.process(&ixs, &[&mint]).await.unwrap();
+    }
+
+    pub async fn create_token_account(&mut self, owner: &Pubkey) -> Pubkey {
+        let rent = self.context.banks_client.get_rent().await.unwrap();
+        let account = Keypair::new();
+
+        let ixs = [
+            system_instruction::create_account(
+                &self.context.payer.pubkey(),
+                &account.pubkey(),
+                rent.minimum_balance(spl_token::state::Account::LEN),
+                spl_token::state::Account::LEN as u64,
+                &spl_token::id(),
+            ),
+            spl_token::instruction::initialize_account(
+                &spl_token::id(),
+                &account.pubkey(),
+                &self.mint.pubkey(),
+                owner,
+            )
+            .unwrap(),
+        ];
+
+        self.process(&ixs, &[&account]).await.unwrap();
+        account.pubkey()
+    }
+
+    pub async fn mint_to(&mut self, account: &Pubkey, amount: u64) {
+        let admin = clone_keypair(&self.admin);
+        let ix = spl_token::instruction::mint_to(
+            &spl_token::id(),
+            &self.mint.pubkey(),
+            account,
+            &admin.pubkey(),
+            &[],
+            amount,
+        )
+        .unwrap();
+
+        self.process(&[ix], &[&admin]).await.unwrap();
+    }
+
+    pub async fn token_balance(&mut self, account: &Pubkey) -> u64 {
+        let account = self
+            .context
+            .banks_client
+            .get_account(*account)
+            .await
+            .unwrap()
+            .unwrap();
+
+        spl_token::state::Account::unpack(&account.data).unwrap().amount
+    }
+
+    pub async fn token_owner(&mut self, account: &Pubkey) -> Pubkey {
+        let account = self
+            .context
+            .banks_client
+            .get_account(*account)
+            .await
+            .unwrap()
+            .unwrap();
+
+        spl_token::state::Account::unpack(&account.data).unwrap().owner
+    }
+
+    pub async fn account<T: AccountDeserialize>(&mut self, address: &Pubkey) -> T {
+        let account = self
+            .context
+            .banks_client
+            .get_account(*address)
+            .await
+            .unwrap()
+            .unwrap();
+
+        T::try_deserialize(&mut account.data.as_ref()).unwrap()
+    }
+
+    pub async fn ruin_staking_account(&mut self) -> RuinStaking {
+        let address = self.ruin_staking;
+        self.account(&address).await
+    }
+
+    pub async fn ruin_staking_term_account(&mut self) -> RuinStakingTerm {
+        let address = self.ruin_staking_term;
+        self.account(&address).await
+    }
+
+    // Copies an account, owner and data included, to a fresh address
+    pub async fn clone_account(&mut self, address: &Pubkey) -> Pubkey {
+        let account = self
+            .context
+            .banks_client
+            .get_account(*address)
+            .await
+            .unwrap()
+            .unwrap();
+
+        let clone = Pubkey::new_unique();
+        self.context.set_account(&clone, &AccountSharedData::from(account));
+        clone
+    }
+
+    // Writes an account directly, for state no instruction can produce anymore
+    pub async fn store_account(&mut self, address: &Pubkey, owner: &Pubkey, data: Vec<u8>) {
+        let rent = self.context.banks_client.get_rent().await.unwrap();
+
+        let account = Account {
+            lamports: rent.minimum_balance(data.len()),
+            data,
+            owner: *owner,
+            ..Account::default()
+        };
+        self.context.set_account(address, &AccountSharedData::from(account));
+    }
+
+    // Overwrites the pool, for counters no test can reasonably drive up
+    pub async fn store_ruin_staking(&mut self, ruin_staking: &RuinStaking) {
+        let mut data = Vec::new();
+        ruin_staking.try_serialize(&mut data).unwrap();
+        data.resize(RuinStaking::LEN, 0);
+
+        let address = self.ruin_staking;
+        self.store_account(&address, &solana_vesting::ID, data).await;
+    }
+
+    // Overwrites the term, for counters no test can reasonably drive up
+    pub async fn store_ruin_staking_term(&mut self, term: &RuinStakingTerm) {
+        let mut data = Vec::new();
+        term.try_serialize(&mut data).unwrap();
+        data.resize(RuinStakingTerm::LEN, 0);
+
+        let address = self.ruin_staking_term;
+        self.store_account(&address, &solana_vesting::ID, data).await;
+    }
+
+    // Pool, term and distributor as the program laid them out before pools and
+    // terms moved to seeded addresses
+    pub async fn create_legacy_pool(
+        &mut self,
+        staking_admin: &Pubkey,
+        lock_duration: i64,
+        rewards: u64,
+    ) -> LegacyPool {
+        let ruin_staking = Pubkey::new_unique();
+        let ruin_staking_term = Pubkey::new_unique();
+        let (distributor, distributor_bump) = Pubkey::find_program_address(
+            &[
+                b"distributor",
+                self.mint.pubkey().as_ref(),
+                staking_admin.as_ref(),
+                [lock_duration as u8].as_ref(),
+            ],
+            &solana_vesting::ID,
+        );
+
+        let legacy_ruin_staking = LegacyRuinStaking {
+            distributor_bump,
+            ruin_staking_bump: 0,
+            staking_admin: *staking_admin,
+            staking_token: self.mint.pubkey(),
+            distributor,
+            treasury: Pubkey::new_unique(),
+            total_staked: 0,
+            paused: false,
+        };
+        let data = legacy_account_data(
+            RuinStaking::discriminator(),
+            &legacy_ruin_staking,
+            LegacyRuinStaking::LEN,
+        );
+        self.store_account(&ruin_staking, &solana_vesting::ID, data).await;
+
+        let legacy_ruin_staking_term = LegacyRuinStakingTerm {
+            ruin_staking,
+            ruin_staking_term_bump: 0,
+            minimum_staking_amount: MINIMUM_STAKING_AMOUNT,
+            maximum_staking_amount: MAXIMUM_STAKING_AMOUNT,
+            start_join_time: START_TIME,
+            end_join_time: START_TIME + 10 * ONE_DAY,
+            lock_duration,
+            delay_duration: DELAY_DURATION,
+            apr: APR,
+            total_slot: TOTAL_SLOT,
+        };
+        let data = legacy_account_data(
+            RuinStakingTerm::discriminator(),
+            &legacy_ruin_staking_term,
+            LegacyRuinStakingTerm::LEN,
+        );
+        self.store_account(&ruin_staking_term, &solana_vesting::ID, data).await;
+
+        let token_account = spl_token::state::Account {
+            mint: self.mint.pubkey(),
+            owner: legacy_vault_authority_address(),
+            amount: rewards,
+            state: spl_token::state::AccountState::Initialized,
+            ..spl_token::state::Account::default()
+        };
+        let mut data = vec![0; spl_token::state::Account::LEN];
+        token_account.pack_into_slice(&mut data);
+        self.store_account(&distributor, &spl_token::id(), data).await;
+
+        LegacyPool {
+            ruin_staking,
+            ruin_staking_term,
+            distributor,
+        }
+    }
+
+    // Adds a term without rewards and points every instruction builder at it,
+    // investors must be created afterwards
+    pub async fn use_unfunded_term(&mut self, term_index: u8) {
+        let term = term_address(&self.ruin_staking, term_index);
+        let admin = clone_keypair(&self.admin);
+        let add_term_ix = self.add_staking_term_ix(&term, LOCK_DURATION);
+        self.process(&[add_term_ix], &[&admin]).await.unwrap();
+
+        self.ruin_staking_term = term;
+        self.distributor = term_distributor(&term);
+    }
+
+    // Mints `amount` to the admin and moves it to the current term's distributor
+    pub async fn fund_distributor(&mut self, amount: u64) {
+        let admin = clone_keypair(&self.admin);
+        let admin_token_account = self.create_token_account(&admin.pubkey()).await;
+        self.mint_to(&admin_token_account, amount).await;
+        let fund_ix = self.fund_distributor_ix(&admin_token_account, amount);
+        self.process(&[fund_ix], &[&admin]).await.unwrap();
+    }
+
+    pub async fn create_investor(&mut self, amount: u64) -> Investor {
+        let keypair = Keypair::new();
+        self.airdrop(&keypair.pubkey(), 1_000_000_000).await;
+
+        let token_account = self.create_token_account(&keypair.pubkey()).await;
+        self.mint_to(&token_account, amount).await;
+
+        let ruin_staking = self.ruin_staking;
+        let ruin_staking_term = self.ruin_staking_term;
+        let investor = keypair.pubkey();
+        let seeds = |prefix: &[u8]| {
+            Pubkey::find_program_address(
+                &[
+                    prefix,
+                    ruin_staking.as_ref(),
+                    ruin_staking_term.as_ref(),
+                    investor.as_ref(),
+                ],
+                &solana_vesting::ID,
+            )
+            .0
+        };
+
+        Investor {
+            user_staked: seeds(b"stake"),
+            user_pending_withdrawl: seeds(b"withdraw_reward"),
+            user_pending_withdraw: seeds(b"withdraw"),
+            keypair,
+            token_account,
+        }
+    }
+
+    pub fn initialize_ix(&self) -> Instruction {
+        Instruction {
+            program_id: solana_vesting::ID,
+            accounts: accounts::InitializeStakingInfo {
+                ruin_staking: self.ruin_staking,
+                ruin_staking_term: self.ruin_staking_term,
+                ruin_staking_admin: self.admin.pubkey(),
+                ruin_staking_token: self.mint.pubkey(),
+                ruin_staking_reward_token: self.mint.pubkey(),
+                treasury_authority: self.treasury_authority,
+                vault_authority: self.vault_authority,
+                ruin_staking_treasury: self.treasury.pubkey(),
+                ruin_staking_distributor: self.distributor,
+                system_program: system_program::id(),
+                token_program: spl_token::id(),
+                rent: sysvar::rent::id(),
+            }
+            .to_account_metas(None),
+            data: instruction::Initialize {
+                pool_id: POOL_ID,
+                minimum_staking_amount: MINIMUM_STAKING_AMOUNT,
+                maximum_staking_amount: MAXIMUM_STAKING_AMOUNT,
+                lock_duration: LOCK_DURATION,
+                start_join_time: START_TIME,
+                end_join_time: START_TIME + 10 * ONE_DAY,
+                delay_duration: DELAY_DURATION,
+                apr: APR,
+                total_slot: TOTAL_SLOT,
+                term_change_delay: TERM_CHANGE_DELAY,
+            }
+            .data(),
+        }
+    }
+
+    pub fn add_staking_term_ix(&self, ruin_staking_term: &Pubkey, lock_duration: i64) -> Instruction {
+        self.add_staking_term_with_delay_ix(ruin_staking_term, lock_duration, TERM_CHANGE_DELAY)
+    }
+
+    pub fn add_staking_term_with_delay_ix(
+        &self,
+        ruin_staking_term: &Pubkey,
+        lock_duration: i64,
+        term_change_delay: i64,
+    ) -> Instruction {
+        Instruction {
+            program_id: solana_vesting::ID,
+            accounts: accounts::AddStakingTerm {
+                ruin_staking: self.ruin_staking,
+                ruin_staking_term: *ruin_staking_term,
+                ruin_staking_admin: self.admin.pubkey(),
+                ruin_staking_token: self.mint.pubkey(),
+                ruin_staking_reward_token: self.mint.pubkey(),
+                vault_authority: self.vault_authority,
+                ruin_staking_distributor: term_distributor(ruin_staking_term),
+                system_program: system_program::id(),
+                token_program: spl_token::id(),
+                rent: sysvar::rent::id(),
+            }
+            .to_account_metas(None),
+            data: instruction::AddStakingTerm {
+                minimum_staking_amount: MINIMUM_STAKING_AMOUNT,
+                maximum_staking_amount: MAXIMUM_STAKING_AMOUNT,
+                lock_duration,
+                start_join_time: START_TIME,
+                end_join_time: START_TIME + 10 * ONE_DAY,
+                delay_duration: DELAY_DURATION,
+                apr: APR,
+                total_slot: TOTAL_SLOT,
+                term_change_delay,
+            }
+            .data(),
+        }
+    }
+
+    pub fn fund_distributor_ix(&self, funder_token_account: &Pubkey, amount: u64) -> Instruction {
+        Instruction {
+            program_id: solana_vesting::ID,
+            accounts: accounts::FundDistributor {
+                ruin_staking: self.ruin_staking,
+                ruin_staking_term: self.ruin_staking_term,
+                distributor_token_account: self.distributor,
+                funder_token_account: *funder_token_account,
+                authority: self.admin.pubkey(),
+                token_program: spl_token::id(),
+            }
+            .to_account_metas(None),
+            data: instruction::FundDistributor { amount }.data(),
+        }
+    }
+
+    pub fn migrate_distributor_ix(&self, legacy_pool: &LegacyPool) -> Instruction {
+        Instruction {
+            program_id: solana_vesting::ID,
+            accounts: accounts::MigrateDistributor {
+                ruin_staking: self.ruin_staking,
+                ruin_staking_term: self.ruin_staking_term,
+                legacy_ruin_staking: legacy_pool.ruin_staking,
+                legacy_ruin_staking_term: legacy_pool.ruin_staking_term,
+                legacy_vault_authority: legacy_vault_authority_address(),
+                legacy_distributor: legacy_pool.distributor,
+                distributor_token_account: self.distributor,
+                ruin_staking_admin: self.admin.pubkey(),
+                token_program: spl_token::id(),
+            }
+            .to_account_metas(None),
+            data: instruction::MigrateDistributor {}.data(),
+        }
+    }
+
+    pub fn set_paused_ix(&self, paused: bool) -> Instruction {
+        self.set_paused_by_ix(&self.admin.pubkey(), paused)
+    }
+
+    pub fn set_paused_by_ix(&self, authority: &Pubkey, paused: bool) -> Instruction {
+        Instruction {
+            program_id: solana_vesting::ID,
+            accounts: accounts::SetPaused {
+                ruin_staking: self.ruin_staking,
+                authority: *authority,
+            }
+            .to_account_metas(None),
+            data: instruction::SetPaused { paused }.data(),
+        }
+    }
+
+    pub fn propose_admin_ix(&self, new_admin: &Pubkey) -> Instruction {
+        Instruction {
+            program_id: solana_vesting::ID,
+            accounts: accounts::ProposeAdmin {
+                ruin_staking: self.ruin_staking,
+                ruin_staking_admin: self.admin.pubkey(),
+            }
+            .to_account_metas(None),
+            data: instruction::ProposeAdmin {
+                new_admin: *new_admin,
+            }
+            .data(),
+        }
+    }
+
+    pub fn accept_admin_ix(&self, pending_admin: &Pubkey) -> Instruction {
+        Instruction {
+            program_id: solana_vesting::ID,
+            accounts: accounts::AcceptAdmin {
+                ruin_staking: self.ruin_staking,
+                pending_admin: *pending_admin,
+            }
+            .to_account_metas(None),
+            data: instruction::AcceptAdmin {}.data(),
+        }
+    }
+
+    pub fn set_roles_ix(&self, pauser: Option<Pubkey>, reward_manager: Option<Pubkey>) -> Instruction {
+        Instruction {
+            program_id: solana_vesting::ID,
+            accounts: accounts::SetRoles {
+                ruin_staking: self.ruin_staking,
+                ruin_staking_admin: self.admin.pubkey(),
+            }
+            .to_account_metas(None),
+            data: instruction::SetRoles {
+                pauser,
+                reward_manager,
+            }
+            .data(),
+        }
+    }
+
+    pub fn update_apr_ix(&self, authority: &Pubkey, apr: u32) -> Instruction {
+        Instruction {
+            program_id: solana_vesting::ID,
+            accounts: accounts::UpdateApr {
+                ruin_staking: self.ruin_staking,
+                ruin_staking_term: self.ruin_staking_term,
+                distributor_token_account: self.distributor,
+                authority: *authority,
+            }
+            .to_account_metas(None),
+            data: instruction::UpdateApr { apr }.data(),
+        }
+    }
+
+    pub fn update_staking_term_ix(&self, delay_duration: i64, apr: u32) -> Instruction {
+        Instruction {
+            program_id: solana_vesting::ID,
+            accounts: accounts::UpdateStakingTerm {
+                ruin_staking: self.ruin_staking,
+                ruin_staking_term: self.ruin_staking_term,
+                distributor_token_account: self.distributor,
+                ruin_staking_admin: self.admin.pubkey(),
+            }
+            .to_account_metas(None),
+            data: instruction::UpdateStakingTerm {
+                minimum_staking_amount: MINIMUM_STAKING_AMOUNT,
+                maximum_staking_amount: MAXIMUM_STAKING_AMOUNT,
+                start_join_time: START_TIME,
+                end_join_time: START_TIME + 10 * ONE_DAY,
+                delay_duration,
+                apr,
+            }
+            .data(),
+        }
+    }
+
+    pub fn set_reward_exchange_rate_ix(&self, numerator: u64, denominator: u64) -> Instruction {
+        Instruction {
+            program_id: solana_vesting::ID,
+            accounts: accounts::SetRewardExchangeRate {
+                ruin_staking: self.ruin_staking,
+                ruin_staking_term: self.ruin_staking_term,
+                authority: self.admin.pubkey(),
+            }
+            .to_account_metas(None),
+            data: instruction::SetRewardExchangeRate {
+                numerator,
+                denominator,
+            }
+            .data(),
+        }
+    }
+
+    pub fn pending_term_change(&self) -> Pubkey {
+        Pubkey::find_program_address(
+            &[b"term_change", self.ruin_staking_term.as_ref()],
+            &solana_vesting::ID,
+        )
+        .0
+    }
+
+    pub fn queue_term_change_ix(
+        &self,
+        apr: u32,
+        term_change_delay: i64,
+        effective_at: i64,
+    ) -> Instruction {
+        self.queue_post_lock_change_ix(
+            apr,
+            PostLockAccrual::Stop,
+            0,
+            term_change_delay,
+            effective_at,
+        )
+    }
+
+    pub fn queue_post_lock_change_ix(
+        &self,
+        apr: u32,
+        post_lock_accrual: PostLockAccrual,
+        post_lock_apr: u32,
+        term_change_delay: i64,
+        effective_at: i64,
+    ) -> Instruction {
+        Instruction {
+            program_id: solana_vesting::ID,
+            accounts: accounts::QueueTermChange {
+                ruin_staking: self.ruin_staking,
+                ruin_staking_term: self.ruin_staking_term,
+                pending_term_change: self.pending_term_change(),
+                ruin_staking_admin: self.admin.pubkey(),
+                system_program: system_program::id(),
+            }
+            .to_account_metas(None),
+            data: instruction::QueueTermChange {
+                minimum_staking_amount: MINIMUM_STAKING_AMOUNT,
+                maximum_staking_amount: MAXIMUM_STAKING_AMOUNT,
+                start_join_time: START_TIME,
+                end_join_time: START_TIME + 10 * ONE_DAY,
+                delay_duration: DELAY_DURATION,
+                apr,
+                term_change_delay,
+                effective_at,
+                post_lock_accrual,
+                post_lock_apr,
+            }
+            .data(),
+        }
+    }
+
+    pub fn execute_term_change_ix(&self) -> Instruction {
+        Instruction {
+            program_id: solana_vesting::ID,
+            accounts: accounts::ExecuteTermChange {
+                ruin_staking: self.ruin_staking,
+                ruin_staking_term: self.ruin_staking_term,
+                distributor_token_account: self.distributor,
+                pending_term_change: self.pending_term_change(),
+                ruin_staking_admin: self.admin.pubkey(),
+            }
+            .to_account_metas(None),
+            data: instruction::ExecuteTermChange {}.data(),
+        }
+    }
+
+    pub fn cancel_term_change_ix(&self) -> Instruction {
+        Instruction {
+            program_id: solana_vesting::ID,
+            accounts: accounts::CancelTermChange {
+                ruin_staking: self.ruin_staking,
+                ruin_staking_term: self.ruin_staking_term,
+                pending_term_change: self.pending_term_change(),
+                ruin_staking_admin: self.admin.pubkey(),
+            }
+            .to_account_metas(None),
+            data: instruction::CancelTermChange {}.data(),
+        }
+    }
+
+    pub fn configure_emission_ix(&self, reward_per_second: u64, emission_end_time: i64) -> Instruction {
+        Instruction {
+            program_id: solana_vesting::ID,
+            accounts: accounts::ConfigureEmission {
+                ruin_staking: self.ruin_staking,
+                ruin_staking_term: self.ruin_staking_term,
+                distributor_token_account: self.distributor,
+                authority: self.admin.pubkey(),
+            }
+            .to_account_metas(None),
+            data: instruction::ConfigureEmission {
+                reward_per_second,
+                emission_end_time,
+            }
+            .data(),
+        }
+    }
+
+    pub fn reward_stream(&self, stream_index: u8) -> Pubkey {
+        Pubkey::find_program_address(
+            &[
+                b"reward_stream",
+                self.ruin_staking_term.as_ref(),
+                [stream_index].as_ref(),
+            ],
+            &solana_vesting::ID,
+        )
+        .0
+    }
+
+    pub fn reward_stream_distributor(&self, stream_index: u8) -> Pubkey {
+        Pubkey::find_program_address(
+            &[b"stream_distributor", self.reward_stream(stream_index).as_ref()],
+            &solana_vesting::ID,
+        )
+        .0
+    }
+
+    pub fn user_reward_stream(&self, stream_index: u8, investor: &Investor) -> Pubkey {
+        Pubkey::find_program_address(
+            &[
+                b"stream_reward",
+                self.reward_stream(stream_index).as_ref(),
+                investor.keypair.pubkey().as_ref(),
+            ],
+            &solana_vesting::ID,
+        )
+        .0
+    }
+
+    // Streams use the staking mint so that investors can reuse their token account
+    pub fn add_reward_stream_ix(
+        &self,
+        stream_index: u8,
+        reward_mode: RewardMode,
+        apr: u32,
+        reward_per_second: u64,
+        end_time: i64,
+    ) -> Instruction {
+        Instruction {
+            program_id: solana_vesting::ID,
+            accounts: accounts::AddRewardStream {
+                ruin_staking: self.ruin_staking,
+                ruin_staking_term: self.ruin_staking_term,
+                reward_stream: self.reward_stream(stream_index),
+                reward_mint: self.mint.pubkey(),
+                vault_authority: self.vault_authority,
+                reward_stream_distributor: self.reward_stream_distributor(stream_index),
+                ruin_staking_admin: self.admin.pubkey(),
+                system_program: system_program::id(),
+                token_program: spl_token::id(),
+                rent: sysvar::rent::id(),
+            }
+            .to_account_metas(None),
+            data: instruction::AddRewardStream {
+                reward_mode,
+                apr,
+                reward_per_second,
+                end_time,
+            }
+            .data(),
+        }
+    }
+
+    pub fn fund_reward_stream_ix(
+        &self,
+        stream_index: u8,
+        funder_token_account: &Pubkey,
+        amount: u64,
+    ) -> Instruction {
+        Instruction {
+            program_id: solana_vesting::ID,
+            accounts: accounts::FundRewardStream {
+                ruin_staking: self.ruin_staking,
+                ruin_staking_term: self.ruin_staking_term,
+                reward_stream: self.reward_stream(stream_index),
+                distributor_token_account: self.reward_stream_distributor(stream_index),
+                funder_token_account: *funder_token_account,
+                authority: self.admin.pubkey(),
+                token_program: spl_token::id(),
+            }
+            .to_account_metas(None),
+            data: instruction::FundRewardStream { amount }.data(),
+        }
+    }
+
+    pub fn join_reward_stream_ix(&self, stream_index: u8, investor: &Investor) -> Instruction {
+        Instruction {
+            program_id: solana_vesting::ID,
+            accounts: accounts::JoinRewardStream {
+                ruin_staking: self.ruin_staking,
+                ruin_staking_term: self.ruin_staking_term,
+                reward_stream: self.reward_stream(stream_index),
+                user_reward_stream: self.user_reward_stream(stream_index, investor),
+                investor: investor.keypair.pubkey(),
+                system_program: system_program::id(),
+            }
+            .to_account_metas(None),
+            data: instruction::JoinRewardStream {}.data(),
+        }
+    }
+
+    // Remaining accounts settling the investor's position in the first
+    // `stream_count` streams.
+    pub fn reward_stream_metas(&self, investor: &Investor, stream_count: u8) -> Vec<AccountMeta> {
+        (0..stream_count)
+            .flat_map(|stream_index| {
+                [
+                    AccountMeta::new(self.reward_stream(stream_index), false),
+                    AccountMeta::new(self.user_reward_stream(stream_index, investor), false),
+                ]
+            })
+            .collect()
+    }
+
+    // Remaining accounts holding only the investor's positions in the first
+    // `stream_count` streams, for instructions that close them.
+    pub fn user_reward_stream_metas(&self, investor: &Investor, stream_count: u8) -> Vec<AccountMeta> {
+        (0..stream_count)
+            .map(|stream_index| {
+                AccountMeta::new(self.user_reward_stream(stream_index, investor), false)
+            })
+            .collect()
+    }
+
+    // Remaining accounts paying out the investor's position in the first
+    // `stream_count` streams.
+    pub fn claim_reward_stream_metas(&self, investor: &Investor, stream_count: u8) -> Vec<AccountMeta> {
+        (0..stream_count)
+            .flat_map(|stream_index| {
+                [
+                    AccountMeta::new(self.reward_stream(stream_index), false),
+                    AccountMeta::new(self.user_reward_stream(stream_index, investor), false),
+                    AccountMeta::new(self.reward_stream_distributor(stream_index), false),
+                    AccountMeta::new(investor.token_account, false),
+                ]
+            })
+            .collect()
+    }
+
+    pub fn set_post_lock_accrual_ix(
+        &self,
+        post_lock_accrual: PostLockAccrual,
+        post_lock_apr: u32,
+    ) -> Instruction {
+        Instruction {
+            program_id: solana_vesting::ID,
+            accounts: accounts::SetPostLockAccrual {
+                ruin_staking: self.ruin_staking,
+                ruin_staking_term: self.ruin_staking_term,
+                ruin_staking_admin: self.admin.pubkey(),
+            }
+            .to_account_metas(None),
+            data: instruction::SetPostLockAccrual {
+                post_lock_accrual,
+                post_lock_apr,
+            }
+            .data(),
+        }
+    }
+
+    pub fn set_early_withdraw_config_ix(
+        &self,
+        penalty_bps: u16,
+        reward_penalty_bps: u16,
+        penalty_destination: PenaltyDestination,
+        penalty_fee_account: &Pubkey,
+    ) -> Instruction {
+        Instruction {
+            program_id: solana_vesting::ID,
+            accounts: accounts::SetEarlyWithdrawConfig {
+                ruin_staking: self.ruin_staking,
+                ruin_staking_term: self.ruin_staking_term,
+                penalty_fee_account: *penalty_fee_account,
+                ruin_staking_admin: self.admin.pubkey(),
+            }
+            .to_account_metas(None),
+            data: instruction::SetEarlyWithdrawConfig {
+                enabled: true,
+                penalty_bps,
+                reward_penalty_bps,
+                penalty_destination,
+            }
+            .data(),
+        }
+    }
+
+    pub fn sweep_penalties_ix(&self, recipient_token_account: &Pubkey) -> Instruction {
+        Instruction {
+            program_id: solana_vesting::ID,
+            accounts: accounts::SweepPenalties {
+                ruin_staking: self.ruin_staking,
+                treasury_authority: self.treasury_authority,
+                treasury_token_account: self.treasury.pubkey(),
+                recipient_token_account: *recipient_token_account,
+                ruin_staking_admin: self.admin.pubkey(),
+                token_program: spl_token::id(),
+            }
+            .to_account_metas(None),
+            data: instruction::SweepPenalties {}.data(),
+        }
+    }
+
+    pub fn stake_ix(&self, investor: &Investor, amount: u64) -> Instruction {
+        Instruction {
+            program_id: solana_vesting::ID,
+            accounts: accounts::Stake {
+                ruin_staking: self.ruin_staking,
+                ruin_staking_term: self.ruin_staking_term,
+                user_staked: investor.user_staked,
+                user_pending_withdrawl: investor.user_pending_withdrawl,
+                investor_token_account: investor.token_account,
+                treasury_token_account: self.treasury.pubkey(),
+                distributor_token_account: self.distributor,
+                investor: investor.keypair.pubkey(),
+                system_program: system_program::id(),
+                token_program: spl_token::id(),
+                rent: sysvar::rent::id(),
+            }
+            .to_account_metas(None),
+            data: instruction::Stake { amount }.data(),
+        }
+    }
+
+    pub fn close_position_ix(&self, investor: &Investor) -> Instruction {
+        Instruction {
+            program_id: solana_vesting::ID,
+            accounts: accounts::ClosePosition {
+                ruin_staking: self.ruin_staking,
+                ruin_staking_term: self.ruin_staking_term,
+                user_staked: investor.user_staked,
+                user_pending_withdrawl: investor.user_pending_withdrawl,
+                user_pending_withdraw: investor.user_pending_withdraw,
+                investor: investor.keypair.pubkey(),
+            }
+            .to_account_metas(None),
+            data: instruction::ClosePosition {}.data(),
+        }
+    }
+
+    pub fn harvest_ix(&self, investor: &Investor) -> Instruction {
+        Instruction {
+            program_id: solana_vesting::ID,
+            accounts: accounts::Harvest {
+                ruin_staking: self.ruin_staking,
+                ruin_staking_term: self.ruin_staking_term,
+                user_staked: investor.user_staked,
+                user_pending_withdrawl: investor.user_pending_withdrawl,
+                treasury_token_account: self.treasury.pubkey(),
+                investor: investor.keypair.pubkey(),
+                system_program: system_program::id(),
+                token_program: spl_token::id(),
+            }
+            .to_account_metas(None),
+            data: instruction::Harvest {}.data(),
+        }
+    }
+
+    pub fn compound_ix(&self, investor: &Investor) -> Instruction {
+        Instruction {
+            program_id: solana_vesting::ID,
+            accounts: accounts::Compound {
+                ruin_staking: self.ruin_staking,
+                ruin_staking_term: self.ruin_staking_term,
+                vault_authority: self.vault_authority,
+                user_staked: investor.user_staked,
+                user_pending_withdrawl: investor.user_pending_withdrawl,
+                distributor_token_account: self.distributor,
+                treasury_token_account: self.treasury.pubkey(),
+                investor: investor.keypair.pubkey(),
+                token_program: spl_token::id(),
+            }
+            .to_account_metas(None),
+            data: instruction::Compound {}.data(),
+        }
+    }
+
+    pub fn claim_pending_reward_ix(&self, investor: &Investor) -> Instruction {
+        Instruction {
+            program_id: solana_vesting::ID,
+            accounts: accounts::ClaimPendingReward {
+                ruin_staking: self.ruin_staking,
+                ruin_staking_term: self.ruin_staking_term,
+                vault_authority: self.vault_authority,
+                user_pending_withdrawl: investor.user_pending_withdrawl,
+                distributor_token_account: self.distributor,
+                investor_token_account: investor.token_account,
+                investor: investor.keypair.pubkey(),
+                token_program: spl_token::id(),
+            }
+            .to_account_metas(None),
+            data: instruction::ClaimPendingReward {}.data(),
+        }
+    }
+
+    pub fn withdraw_ix(&self, investor: &Investor, amount: u64) -> Instruction {
+        Instruction {
+            program_id: solana_vesting::ID,
+            accounts: accounts::Withdraw {
+                ruin_staking: self.ruin_staking,
+                ruin_staking_term: self.ruin_staking_term,
+                user_staked: investor.user_staked,
+                user_pending_withdrawl: investor.user_pending_withdrawl,
+                user_pending_withdraw: investor.user_pending_withdraw,
+                treasury_token_account: self.treasury.pubkey(),
+                investor_token_account: investor.token_account,
+                investor: investor.keypair.pubkey(),
+                system_program: system_program::id(),
+                token_program: spl_token::id(),
+            }
+            .to_account_metas(None),
+            data: instruction::Withdraw { amount }.data(),
+        }
+    }
+
+    pub fn early_withdraw_ix(
+        &self,
+        investor: &Investor,
+        penalty_token_account: &Pubkey,
+        amount: u64,
+    ) -> Instruction {
+        Instruction {
+            program_id: solana_vesting::ID,
+            accounts: accounts::EarlyWithdraw {
+                ruin_staking: self.ruin_staking,
+                ruin_staking_term: self.ruin_staking_term,
+                treasury_authority: self.treasury_authority,
+                user_staked: investor.user_staked,
+                user_pending_withdrawl: investor.user_pending_withdrawl,
+                treasury_token_account: self.treasury.pubkey(),
+                penalty_token_account: *penalty_token_account,
+                investor_token_account: investor.token_account,
+                investor: investor.keypair.pubkey(),
+                token_program: spl_token::id(),
+            }
+            .to_account_metas(None),
+            data: instruction::EarlyWithdraw { amount }.data(),
+        }
+    }
+
+    pub fn emergency_withdraw_ix(&self, investor: &Investor) -> Instruction {
+        Instruction {
+            program_id: solana_vesting::ID,
+            accounts: accounts::EmergencyWithdraw {
+                ruin_staking: self.ruin_staking,
+                ruin_staking_term: self.ruin_staking_term,
+                treasury_authority: self.treasury_authority,
+                user_staked: investor.user_staked,
+                user_pending_withdrawl: investor.user_pending_withdrawl,
+                user_pending_withdraw: investor.user_pending_withdraw,
+                treasury_token_account: self.treasury.pubkey(),
+                investor_token_account: investor.token_account,
+                investor: investor.keypair.pubkey(),
+                token_program: spl_token::id(),
+            }
+            .to_account_metas(None),
+            data: instruction::EmergencyWithdraw {}.data(),
+        }
+    }
+
+    pub fn claim_pending_withdrawl_ix(&self, investor: &Investor) -> Instruction {
+        Instruction {
+            program_id: solana_vesting::ID,
+            accounts: accounts::ClaimPendingWithdrawl {
+                ruin_staking: self.ruin_staking,
+                ruin_staking_term: self.ruin_staking_term,
+                treasury_authority: self.treasury_authority,
+                user_pending_withdraw: investor.user_pending_withdraw,
+                treasury_token_account: self.treasury.pubkey(),
+                investor_token_account: investor.token_account,
+                investor: investor.keypair.pubkey(),
+                token_program: spl_token::id(),
+            }
+            .to_account_metas(None),
+            data: instruction::ClaimPendingWithdrawl {}.data(),
+        }
+    }
+}
+
+pub fn pool_address(mint: &Pubkey, admin: &Pubkey, pool_id: u64) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            b"staking",
+            mint.as_ref(),
+            admin.as_ref(),
+            pool_id.to_le_bytes().as_ref(),
+        ],
+        &solana_vesting::ID,
+    )
+    .0
+}
+
+pub fn term_address(ruin_staking: &Pubkey, term_index: u8) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"term", ruin_staking.as_ref(), [term_index].as_ref()],
+        &solana_vesting::ID,
+    )
+    .0
+}
+
+pub fn term_distributor(ruin_staking_term: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"distributor", ruin_staking_term.as_ref()],
+        &solana_vesting::ID,
+    )
+    .0
+}
+
+pub fn clone_keypair(keypair: &Keypair) -> Keypair {
+    Keypair::from_bytes(&keypair.to_bytes()).unwrap()
+}
@@ -0,0 +1,517 @@
+use proptest::prelude::*;
+use solana_vesting::account::{AprCheckpoint, PostLockAccrual};
+use solana_vesting::helpers::{DIV_PRECISION, ONE_YEAR_IN_SECONDS};
+use solana_vesting::reward::{
+    calculate_reward, effective_post_lock_apr, emission_per_share, emission_reward, mul_div,
+    reward_for_duration, reward_for_period, to_reward_units,
+};
+
+const ONE_DAY: i64 = 24 * 60 * 60;
+const TEN_YEARS: i64 = 10 * 365 * ONE_DAY;
+const JOIN_TIME: i64 = 1_650_000_000;
+const LOCK_DURATION: i64 = 30 * ONE_DAY;
+const LOCK_END: i64 = JOIN_TIME + LOCK_DURATION;
+const AMOUNT: u64 = 1_000_000;
+const APR: u32 = 1_000;
+const POST_LOCK_APR: u32 = 250;
+
+// 1 billion tokens with 6 decimals, 1000% APR over ten years fits in u128
+const MAX_AMOUNT: u64 = 1_000_000_000_000_000;
+const MAX_APR: u32 = 100_000;
+
+fn fixed_apr(apr: u32) -> [AprCheckpoint; 1] {
+    [AprCheckpoint { timestamp: 0, apr }]
+}
+
+fn reward_at(post_lock_accrual: PostLockAccrual, current_time: i64) -> u128 {
+    calculate_reward(
+        AMOUNT,
+        &fixed_apr(APR),
+        effective_post_lock_apr(post_lock_accrual, POST_LOCK_APR),
+        JOIN_TIME,
+        JOIN_TIME,
+        current_time,
+        LOCK_DURATION,
+    )
+    .unwrap()
+}
+
+#[test]
+fn reward_for_one_year_matches_apr() {
+    let reward = reward_for_duration(1_000_000, 1_000, ONE_YEAR_IN_SECONDS as i64).unwrap();
+
+    assert_eq!(reward, 100_000 * u128::from(DIV_PRECISION));
+}
+
+#[test]
+fn fresh_stake_earns_nothing() {
+    let reward = calculate_reward(
+        AMOUNT,
+        &fixed_apr(APR),
+        None,
+        JOIN_TIME,
+        0,
+        JOIN_TIME + ONE_DAY,
+        0,
+    )
+    .unwrap();
+
+    assert_eq!(reward, 0);
+}
+
+#[test]
+fn overflow_is_reported_as_error() {
+    let result = reward_for_duration(u64::MAX, u32::MAX, i64::MAX);
+
+    assert!(result.is_err());
+}
+
+#[test]
+fn reward_units_follow_exchange_ratio() {
+    let reward = reward_for_duration(AMOUNT, APR, ONE_YEAR_IN_SECONDS as i64).unwrap();
+
+    assert_eq!(to_reward_units(reward, 1, 1).unwrap(), reward);
+    assert_eq!(to_reward_units(reward, 5, 2).unwrap(), reward * 5 / 2);
+    assert_eq!(to_reward_units(reward, 1, 4).unwrap(), reward / 4);
+    assert!(to_reward_units(u128::MAX, 2, 1).is_err());
+}
+
+#[test]
+fn every_mode_accrues_apr_until_lock_end() {
+    for mode in [
+        PostLockAccrual::Stop,
+        PostLockAccrual::PostLockApr,
+        PostLockAccrual::SameApr,
+    ] {
+        let before_lock_end = reward_at(mode, LOCK_END - 1);
+        let at_lock_end = reward_at(mode, LOCK_END);
+
+        assert_eq!(
+            before_lock_end,
+            reward_for_duration(AMOUNT, APR, LOCK_DURATION - 1).unwrap()
+        );
+        assert_eq!(
+            at_lock_end,
+            reward_for_duration(AMOUNT, APR, LOCK_DURATION).unwrap()
+        );
+    }
+}
+
+#[test]
+fn stop_mode_caps_reward_at_lock_end() {
+    let at_lock_end = reward_at(PostLockAccrual::Stop, LOCK_END);
+
+    assert_eq!(reward_at(PostLockAccrual::Stop, LOCK_END + 1), at_lock_end);
+    assert_eq!(
+        reward_at(PostLockAccrual::Stop, LOCK_END + TEN_YEARS),
+        at_lock_end
+    );
+}
+
+#[test]
+fn post_lock_apr_mode_switches_rate_at_lock_end() {
+    let at_lock_end = reward_at(PostLockAccrual::PostLockApr, LOCK_END);
+    let after_lock_end = reward_at(PostLockAccrual::PostLockApr, LOCK_END + ONE_DAY);
+
+    assert_eq!(
+        after_lock_end,
+        at_lock_end + reward_for_duration(AMOUNT, POST_LOCK_APR, ONE_DAY).unwrap()
+    );
+}
+
+#[test]
+fn same_apr_mode_ignores_lock_end() {
+    let after_lock_end = reward_at(PostLockAccrual::SameApr, LOCK_END + ONE_DAY);
+
+    assert_eq!(
+        after_lock_end,
+        reward_for_duration(AMOUNT, APR, LOCK_DURATION).unwrap()
+            + reward_for_duration(AMOUNT, APR, ONE_DAY).unwrap()
+    );
+}
+
+#[test]
+fn update_after_lock_end_only_earns_post_lock_rate() {
+    let updated_time = LOCK_END + ONE_DAY;
+
+    let reward_since_update = |mode| {
+        calculate_reward(
+            AMOUNT,
+            &fixed_apr(APR),
+            effective_post_lock_apr(mode, POST_LOCK_APR),
+            JOIN_TIME,
+            updated_time,
+            updated_time + ONE_DAY,
+            LOCK_DURATION,
+        )
+        .unwrap()
+    };
+
+    assert_eq!(reward_since_update(PostLockAccrual::Stop), 0);
+    assert_eq!(
+        reward_since_update(PostLockAccrual::PostLockApr),
+        reward_for_duration(AMOUNT, POST_LOCK_APR, ONE_DAY).unwrap()
+    );
+    assert_eq!(
+        reward_since_update(PostLockAccrual::SameApr),
+        reward_for_duration(AMOUNT, APR, ONE_DAY).unwrap()
+    );
+}
+
+#[test]
+fn short_delay_does_not_cap_accrual() {
+    // A one second reward delay used to end accrual at join_time + delay_duration
+    let reward = calculate_reward(
+        AMOUNT,
+        &fixed_apr(APR),
+        Some(0),
+        JOIN_TIME,
+        JOIN_TIME,
+        LOCK_END + ONE_DAY,
+        LOCK_DURATION,
+    )
+    .unwrap();
+
+    assert_eq!(
+        reward,
+        reward_for_duration(AMOUNT, APR, LOCK_DURATION).unwrap()
+    );
+}
+
+#[test]
+fn unlocked_term_accrues_apr_indefinitely() {
+    let reward = calculate_reward(
+        AMOUNT,
+        &fixed_apr(APR),
+        Some(0),
+        JOIN_TIME,
+        JOIN_TIME,
+        JOIN_TIME + TEN_YEARS,
+        0,
+    )
+    .unwrap();
+
+    assert_eq!(reward, reward_for_duration(AMOUNT, APR, TEN_YEARS).unwrap());
+}
+
+#[test]
+fn apr_change_applies_from_its_checkpoint() {
+    let checkpoints = [
+        AprCheckpoint {
+            timestamp: 0,
+            apr: APR,
+        },
+        AprCheckpoint {
+            timestamp: JOIN_TIME + 10 * ONE_DAY,
+            apr: APR / 2,
+        },
+    ];
+
+    let reward =
+        reward_for_period(AMOUNT, &checkpoints, JOIN_TIME, JOIN_TIME + 20 * ONE_DAY).unwrap();
+
+    assert_eq!(
+        reward,
+        reward_for_duration(AMOUNT, APR, 10 * ONE_DAY).unwrap()
+            + reward_for_duration(AMOUNT, APR / 2, 10 * ONE_DAY).unwrap()
+    );
+}
+
+#[test]
+fn checkpoints_before_last_update_are_ignored() {
+    let checkpoints = [
+        AprCheckpoint {
+            timestamp: 0,
+            apr: APR,
+        },
+        AprCheckpoint {
+            timestamp: JOIN_TIME,
+            apr: APR * 2,
+        },
+        AprCheckpoint {
+            timestamp: JOIN_TIME + ONE_DAY,
+            apr: APR / 2,
+        },
+    ];
+
+    let reward = reward_for_period(
+        AMOUNT,
+        &checkpoints,
+        JOIN_TIME + 2 * ONE_DAY,
+        JOIN_TIME + 3 * ONE_DAY,
+    )
+    .unwrap();
+
+    assert_eq!(
+        reward,
+        reward_for_duration(AMOUNT, APR / 2, ONE_DAY).unwrap()
+    );
+}
+
+#[test]
+fn oldest_checkpoint_covers_earlier_time() {
+    let checkpoints = [AprCheckpoint {
+        timestamp: JOIN_TIME + ONE_DAY,
+        apr: APR,
+    }];
+
+    let reward =
+        reward_for_period(AMOUNT, &checkpoints, JOIN_TIME, JOIN_TIME + 2 * ONE_DAY).unwrap();
+
+    assert_eq!(
+        reward,
+        reward_for_duration(AMOUNT, APR, 2 * ONE_DAY).unwrap()
+    );
+}
+
+#[test]
+fn same_apr_mode_follows_checkpoints_after_lock_end() {
+    let checkpoints = [
+        AprCheckpoint {
+            timestamp: 0,
+            apr: APR,
+        },
+        AprCheckpoint {
+            timestamp: LOCK_END + ONE_DAY,
+            apr: APR * 2,
+        },
+    ];
+
+    let reward = calculate_reward(
+        AMOUNT,
+        &checkpoints,
+        effective_post_lock_apr(PostLockAccrual::SameApr, POST_LOCK_APR),
+        JOIN_TIME,
+        JOIN_TIME,
+        LOCK_END + 2 * ONE_DAY,
+        LOCK_DURATION,
+    )
+    .unwrap();
+
+    assert_eq!(
+        reward,
+        reward_for_duration(AMOUNT, APR, LOCK_DURATION + ONE_DAY).unwrap()
+            + reward_for_duration(AMOUNT, APR * 2, ONE_DAY).unwrap()
+    );
+}
+
+#[test]
+fn emission_pauses_without_stakers() {
+    assert_eq!(emission_per_share(1_000, ONE_DAY, 0).unwrap(), 0);
+}
+
+#[test]
+fn emission_reward_excludes_debt() {
+    let acc_reward_per_share = emission_per_share(1_000, ONE_DAY, u128::from(AMOUNT)).unwrap();
+    let reward_debt = u128::from(AMOUNT) * acc_reward_per_share;
+
+    let reward = emission_reward(AMOUNT, acc_reward_per_share * 2, reward_debt).unwrap();
+
+    assert_eq!(reward, 1_000 * ONE_DAY as u128 * u128::from(DIV_PRECISION));
+}
+
+#[test]
+fn mul_div_scales_values_whose_product_overflows() {
+    // A year of rewards on 4 million tokens with 9 decimals, grown by an APR raise
+    let reserved = reward_for_duration(4_000_000_000_000_000, APR, 365 * ONE_DAY).unwrap();
+    let index = u128::from(DIV_PRECISION);
+    assert!(reserved.checked_mul(index).is_none());
+
+    assert_eq!(mul_div(reserved, index * 3 / 2, index).unwrap(), reserved * 3 / 2);
+    assert!(mul_div(reserved, index, 0).is_err());
+}
+
+fn post_lock_accrual() -> impl Strategy<Value = PostLockAccrual> {
+    prop_oneof![
+        Just(PostLockAccrual::Stop),
+        Just(PostLockAccrual::PostLockApr),
+        Just(PostLockAccrual::SameApr),
+    ]
+}
+
+proptest! {
+    #[test]
+    fn reward_is_monotonic_in_time(
+        mode in post_lock_accrual(),
+        amount in 0..MAX_AMOUNT,
+        apr in 1..MAX_APR,
+        post_lock_apr in 0..MAX_APR,
+        lock_duration in 0..TEN_YEARS,
+        first in 0..TEN_YEARS,
+        second in 0..TEN_YEARS,
+    ) {
+        let (earlier, later) = if first <= second { (first, second) } else { (second, first) };
+
+        let reward_at = |elapsed: i64| calculate_reward(
+            amount,
+            &fixed_apr(apr),
+            effective_post_lock_apr(mode, post_lock_apr),
+            JOIN_TIME,
+            JOIN_TIME,
+            JOIN_TIME + elapsed,
+            lock_duration,
+        ).unwrap();
+
+        prop_assert!(reward_at(earlier) <= reward_at(later));
+    }
+
+    #[test]
+    fn reward_is_additive_across_updates(
+        mode in post_lock_accrual(),
+        amount in 0..MAX_AMOUNT,
+        apr in 1..MAX_APR,
+        post_lock_apr in 0..MAX_APR,
+        lock_duration in 1..TEN_YEARS,
+        first in 0..TEN_YEARS,
+        second in 0..TEN_YEARS,
+    ) {
+        let (earlier, later) = if first <= second { (first, second) } else { (second, first) };
+        let post_lock_apr = effective_post_lock_apr(mode, post_lock_apr);
+
+        let reward_between = |from: i64, to: i64| calculate_reward(
+            amount,
+            &fixed_apr(apr),
+            post_lock_apr,
+            JOIN_TIME,
+            JOIN_TIME + from,
+            JOIN_TIME + to,
+            lock_duration,
+        ).unwrap();
+
+        let whole = reward_between(0, later);
+        let split = reward_between(0, earlier) + reward_between(earlier, later);
+
+        // Each piece rounds down on its own, so splitting can lose less than a token
+        prop_assert!(split <= whole);
+        prop_assert!(whole - split < u128::from(DIV_PRECISION));
+    }
+
+    #[test]
+    fn reward_is_split_at_checkpoints(
+        amount in 0..MAX_AMOUNT,
+        first_apr in 0..MAX_APR,
+        second_apr in 0..MAX_APR,
+        change_at in 0..TEN_YEARS,
+        elapsed in 0..TEN_YEARS,
+    ) {
+        let checkpoints = [
+            AprCheckpoint { timestamp: 0, apr: first_apr },
+            AprCheckpoint { timestamp: JOIN_TIME + change_at, apr: second_apr },
+        ];
+        let end_time = JOIN_TIME + elapsed;
+
+        let reward = reward_for_period(amount, &checkpoints, JOIN_TIME, end_time).unwrap();
+        let expected = reward_for_duration(amount, first_apr, elapsed.min(change_at)).unwrap()
+            + reward_for_duration(amount, second_apr, elapsed - change_at).unwrap();
+
+        prop_assert_eq!(reward, expected);
+    }
+
+    #[test]
+    fn emission_never_exceeds_budget(
+        reward_per_second in 0..1_000_000_000u64,
+        duration in 0..TEN_YEARS,
+        stakes in proptest::collection::vec(1..MAX_AMOUNT, 1..8),
+    ) {
+        let total_staked: u128 = stakes.iter().map(|stake| u128::from(*stake)).sum();
+        let acc_reward_per_share = emission_per_share(reward_per_second, duration, total_staked).unwrap();
+
+        let distributed: u128 = stakes
+            .iter()
+            .map(|stake| emission_reward(*stake, acc_reward_per_share, 0).unwrap())
+            .sum();
+        let budget = u128::from(reward_per_second) * duration as u128 * u128::from(DIV_PRECISION);
+
+        prop_assert!(distributed <= budget);
+    }
+
+    #[test]
+    fn reward_is_monotonic_in_amount_and_apr(
+        amount in 0..MAX_AMOUNT,
+        extra_amount in 0..MAX_AMOUNT,
+        apr in 1..MAX_APR,
+        extra_apr in 0..MAX_APR,
+        elapsed in 0..TEN_YEARS,
+    ) {
+        let reward = reward_for_duration(amount, apr, elapsed).unwrap();
+        let more_amount = reward_for_duration(amount.saturating_add(extra_amount), apr, elapsed).unwrap();
+        let more_apr = reward_for_duration(amount, apr.saturating_add(extra_apr), elapsed).unwrap();
+
+        prop_assert!(reward <= more_amount);
+        prop_assert!(reward <= more_apr);
+    }
+
+    #[test]
+    fn reward_does_not_overflow_within_bounds(
+        mode in post_lock_accrual(),
+        amount in 0..=MAX_AMOUNT,
+        apr in 0..=MAX_APR,
+        post_lock_apr in 0..=MAX_APR,
+        elapsed in 0..=TEN_YEARS,
+        lock_duration in 0..=TEN_YEARS,
+    ) {
+        prop_assert!(calculate_reward(
+            amount,
+            &fixed_apr(apr),
+            effective_post_lock_apr(mode, post_lock_apr),
+            JOIN_TIME,
+            JOIN_TIME,
+            JOIN_TIME + elapsed,
+            lock_duration,
+        ).is_ok());
+    }
+
+    #[test]
+    fn reward_never_panics(
+        amount in any::<u64>(),
+        apr in any::<u32>(),
+        post_lock_apr in any::<u32>(),
+        join_time in any::<i64>(),
+        updated_time in any::<i64>(),
+        current_time in any::<i64>(),
+        lock_duration in any::<i64>(),
+    ) {
+        let _ = calculate_reward(
+            amount,
+            &fixed_apr(apr),
+            Some(post_lock_apr),
+            join_time,
+            updated_time,
+            current_time,
+            lock_duration,
+        );
+    }
+
+    #[test]
+    fn mul_div_matches_full_product(
+        value in 0..u128::from(u64::MAX),
+        numerator in 0..u128::from(u64::MAX),
+        denominator in 1..u128::from(u64::MAX),
+    ) {
+        prop_assert_eq!(mul_div(value, numerator, denominator).unwrap(), value * numerator / denominator);
+    }
+
+    #[test]
+    fn stop_mode_stops_growing_after_lock_end(
+        amount in 0..MAX_AMOUNT,
+        apr in 1..MAX_APR,
+        post_lock_apr in 0..MAX_APR,
+        lock_duration in 1..TEN_YEARS,
+        after_lock in 0..TEN_YEARS,
+        further in 0..TEN_YEARS,
+    ) {
+        let lock_end = JOIN_TIME + lock_duration;
+
+        let reward_at = |current_time: i64| calculate_reward(
+            amount,
+            &fixed_apr(apr),
+            effective_post_lock_apr(PostLockAccrual::Stop, post_lock_apr),
+            JOIN_TIME,
+            JOIN_TIME,
+            current_time,
+            lock_duration,
+        ).unwrap();
+
+        prop_assert_eq!(reward_at(lock_end + after_lock), reward_at(lock_end + after_lock + further));
+    }
+}
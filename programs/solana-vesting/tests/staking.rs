@@ -0,0 +1,1170 @@
+// Anchor account initialization needs account reallocation, which the native
+// program-test runtime does not support. Build the program first with
+// `anchor build`, then run the suite against the BPF binary with `cargo test-bpf`.
+#![cfg(feature = "test-bpf")]
+
+mod common;
+
+use anchor_lang::error::ErrorCode;
+use common::*;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_vesting::account::*;
+use solana_vesting::error::ProgramErrorCode;
+use solana_vesting::helpers::{DIV_PRECISION, ONE_YEAR_IN_SECONDS};
+
+const STAKE_AMOUNT: u64 = 100_000_000;
+
+fn expected_reward(amount: u64, seconds: i64) -> u128 {
+    expected_reward_at(amount, APR, seconds)
+}
+
+fn expected_reward_at(amount: u64, apr: u32, seconds: i64) -> u128 {
+    u128::from(amount) * seconds as u128 * u128::from(apr) / 10000 * u128::from(DIV_PRECISION)
+        / u128::from(ONE_YEAR_IN_SECONDS)
+}
+
+#[tokio::test]
+async fn stake_moves_tokens_to_treasury() {
+    let mut env = TestEnv::new().await;
+    let investor = env.create_investor(STAKE_AMOUNT).await;
+
+    let stake_ix = env.stake_ix(&investor, STAKE_AMOUNT);
+    env.process(&[stake_ix], &[&investor.keypair]).await.unwrap();
+
+    assert_eq!(env.token_balance(&investor.token_account).await, 0);
+    assert_eq!(env.token_balance(&env.treasury.pubkey()).await, STAKE_AMOUNT);
+    assert_eq!(
+        env.token_owner(&env.treasury.pubkey()).await,
+        env.treasury_authority
+    );
+
+    let user_staked: UserStaked = env.account(&investor.user_staked).await;
+    assert_eq!(user_staked.total_staked, STAKE_AMOUNT);
+    assert_eq!(user_staked.join_time, START_TIME);
+    assert_eq!(user_staked.updated_time, START_TIME);
+
+    let ruin_staking: RuinStaking = env.ruin_staking_account().await;
+    assert_eq!(ruin_staking.total_staked, u128::from(STAKE_AMOUNT));
+
+    let term: RuinStakingTerm = env.ruin_staking_term_account().await;
+    assert_eq!(term.total_staked, u128::from(STAKE_AMOUNT));
+    assert_eq!(term.active_stakers, 1);
+    assert_eq!(term.total_funded, DISTRIBUTOR_FUNDS);
+    assert_eq!(term.reward_liability, expected_reward(STAKE_AMOUNT, LOCK_DURATION));
+}
+
+#[tokio::test]
+async fn stake_rejects_amount_below_minimum() {
+    let mut env = TestEnv::new().await;
+    let investor = env.create_investor(STAKE_AMOUNT).await;
+
+    let stake_ix = env.stake_ix(&investor, MINIMUM_STAKING_AMOUNT - 1);
+    let result = env.process(&[stake_ix], &[&investor.keypair]).await;
+
+    assert_program_error(result, ProgramErrorCode::MinimumAmountNotReached);
+}
+
+#[tokio::test]
+async fn stake_rejects_outside_join_window() {
+    let mut env = TestEnv::new().await;
+    let investor = env.create_investor(STAKE_AMOUNT).await;
+
+    env.set_time(START_TIME + 11 * ONE_DAY).await;
+
+    let stake_ix = env.stake_ix(&investor, STAKE_AMOUNT);
+    let result = env.process(&[stake_ix], &[&investor.keypair]).await;
+
+    assert_program_error(result, ProgramErrorCode::StakingNotStartedOrEnded);
+}
+
+#[tokio::test]
+async fn unlimited_slots_count_past_256_stakers() {
+    let mut env = TestEnv::new().await;
+    let investor = env.create_investor(STAKE_AMOUNT).await;
+
+    let mut term: RuinStakingTerm = env.ruin_staking_term_account().await;
+    term.total_slot = 0;
+    term.active_stakers = u8::MAX.into();
+    env.store_ruin_staking_term(&term).await;
+
+    let stake_ix = env.stake_ix(&investor, STAKE_AMOUNT);
+    env.process(&[stake_ix], &[&investor.keypair]).await.unwrap();
+
+    let term: RuinStakingTerm = env.ruin_staking_term_account().await;
+    assert_eq!(term.active_stakers, 256);
+}
+
+#[tokio::test]
+async fn stake_rejects_when_slots_are_full() {
+    let mut env = TestEnv::new().await;
+
+    for _ in 0..TOTAL_SLOT {
+        let investor = env.create_investor(STAKE_AMOUNT).await;
+        let stake_ix = env.stake_ix(&investor, STAKE_AMOUNT);
+        env.process(&[stake_ix], &[&investor.keypair]).await.unwrap();
+    }
+
+    let investor = env.create_investor(STAKE_AMOUNT).await;
+    let stake_ix = env.stake_ix(&investor, STAKE_AMOUNT);
+    let result = env.process(&[stake_ix], &[&investor.keypair]).await;
+
+    assert_program_error(result, ProgramErrorCode::StakingSlotsFull);
+}
+
+#[tokio::test]
+async fn stake_rejects_when_paused() {
+    let mut env = TestEnv::new().await;
+    let investor = env.create_investor(STAKE_AMOUNT).await;
+
+    let admin = clone_keypair(&env.admin);
+    let pause_ix = env.set_paused_ix(true);
+    env.process(&[pause_ix], &[&admin]).await.unwrap();
+
+    let stake_ix = env.stake_ix(&investor, STAKE_AMOUNT);
+    let result = env.process(&[stake_ix], &[&investor.keypair]).await;
+
+    assert_program_error(result, ProgramErrorCode::StakingPaused);
+}
+
+#[tokio::test]
+async fn harvest_accrues_reward_for_elapsed_time() {
+    let mut env = TestEnv::new().await;
+    let investor = env.create_investor(STAKE_AMOUNT).await;
+
+    let stake_ix = env.stake_ix(&investor, STAKE_AMOUNT);
+    env.process(&[stake_ix], &[&investor.keypair]).await.unwrap();
+
+    env.set_time(START_TIME + 10 * ONE_DAY).await;
+
+    let harvest_ix = env.harvest_ix(&investor);
+    env.process(&[harvest_ix], &[&investor.keypair]).await.unwrap();
+
+    let pending: PendingRewardWithdrawl = env.account(&investor.user_pending_withdrawl).await;
+    assert_eq!(pending.pending_rewards, expected_reward(STAKE_AMOUNT, 10 * ONE_DAY));
+    assert_eq!(pending.claimable_at, START_TIME + 10 * ONE_DAY + DELAY_DURATION);
+
+    let user_staked: UserStaked = env.account(&investor.user_staked).await;
+    assert_eq!(user_staked.updated_time, START_TIME + 10 * ONE_DAY);
+
+    let term: RuinStakingTerm = env.ruin_staking_term_account().await;
+    assert_eq!(term.total_rewards_accrued, pending.pending_rewards);
+}
+
+#[tokio::test]
+async fn harvest_applies_apr_changes_piecewise() {
+    let mut env = TestEnv::new().await;
+    let investor = env.create_investor(STAKE_AMOUNT).await;
+
+    let stake_ix = env.stake_ix(&investor, STAKE_AMOUNT);
+    env.process(&[stake_ix], &[&investor.keypair]).await.unwrap();
+
+    env.set_time(START_TIME + 5 * ONE_DAY).await;
+    let admin = clone_keypair(&env.admin);
+    let apr_ix = env.update_apr_ix(&admin.pubkey(), APR * 2);
+    env.process(&[apr_ix], &[&admin]).await.unwrap();
+
+    env.set_time(START_TIME + 10 * ONE_DAY).await;
+    let harvest_ix = env.harvest_ix(&investor);
+    env.process(&[harvest_ix], &[&investor.keypair]).await.unwrap();
+
+    let pending: PendingRewardWithdrawl = env.account(&investor.user_pending_withdrawl).await;
+    assert_eq!(
+        pending.pending_rewards,
+        expected_reward(STAKE_AMOUNT, 5 * ONE_DAY)
+            + expected_reward_at(STAKE_AMOUNT, APR * 2, 5 * ONE_DAY)
+    );
+}
+
+#[tokio::test]
+async fn apr_raise_must_be_covered_by_distributor() {
+    let mut env = TestEnv::new().await;
+    env.use_unfunded_term(1).await;
+    let investor = env.create_investor(STAKE_AMOUNT).await;
+
+    let reserved =
+        (expected_reward(STAKE_AMOUNT, LOCK_DURATION) / u128::from(DIV_PRECISION)) as u64 + 1;
+    env.fund_distributor(reserved).await;
+
+    let stake_ix = env.stake_ix(&investor, STAKE_AMOUNT);
+    env.process(&[stake_ix], &[&investor.keypair]).await.unwrap();
+
+    let admin = clone_keypair(&env.admin);
+    let apr_ix = env.update_apr_ix(&admin.pubkey(), APR * 2);
+    let result = env.process(&[apr_ix], &[&admin]).await;
+    assert_program_error(result, ProgramErrorCode::InsufficientRewardFunds);
+}
+
+#[tokio::test]
+async fn apr_raise_grows_reservations_of_existing_stakers() {
+    let mut env = TestEnv::new().await;
+    env.use_unfunded_term(1).await;
+    let investor = env.create_investor(STAKE_AMOUNT).await;
+    let other = env.create_investor(STAKE_AMOUNT).await;
+
+    let raised_reserve = expected_reward_at(STAKE_AMOUNT, APR * 2, LOCK_DURATION);
+    env.fund_distributor((raised_reserve / u128::from(DIV_PRECISION)) as u64 + 1)
+        .await;
+
+    let stake_ix = env.stake_ix(&investor, STAKE_AMOUNT);
+    env.process(&[stake_ix], &[&investor.keypair]).await.unwrap();
+
+    let admin = clone_keypair(&env.admin);
+    let apr_ix = env.update_apr_ix(&admin.pubkey(), APR * 2);
+    env.process(&[apr_ix], &[&admin]).await.unwrap();
+
+    let term: RuinStakingTerm = env.ruin_staking_term_account().await;
+    assert!(term.reward_liability >= raised_reserve);
+
+    // Would fit if the first stake were still reserved at the old APR
+    let stake_ix = env.stake_ix(&other, STAKE_AMOUNT / 2);
+    let result = env.process(&[stake_ix], &[&other.keypair]).await;
+    assert_program_error(result, ProgramErrorCode::InsufficientRewardFunds);
+}
+
+#[tokio::test]
+async fn large_stakes_can_refresh_their_reservation() {
+    let mut env = TestEnv::new().await;
+    // 100 million tokens with 9 decimals
+    let amount = 100_000_000_000_000_000;
+    let investor = env.create_investor(amount).await;
+
+    let reserved = expected_reward(amount, LOCK_DURATION);
+    env.fund_distributor((reserved / u128::from(DIV_PRECISION)) as u64)
+        .await;
+
+    let stake_ix = env.stake_ix(&investor, amount);
+    env.process(&[stake_ix], &[&investor.keypair]).await.unwrap();
+
+    env.set_time(START_TIME + ONE_DAY).await;
+    let harvest_ix = env.harvest_ix(&investor);
+    env.process(&[harvest_ix], &[&investor.keypair]).await.unwrap();
+
+    env.set_time(START_TIME + LOCK_DURATION + 1).await;
+    let withdraw_ix = env.withdraw_ix(&investor, amount);
+    env.process(&[withdraw_ix], &[&investor.keypair])
+        .await
+        .unwrap();
+
+    let term: RuinStakingTerm = env.ruin_staking_term_account().await;
+    assert_eq!(term.total_reserved_reward, 0);
+}
+
+#[tokio::test]
+async fn harvest_applies_reward_exchange_rate() {
+    let mut env = TestEnv::new().await;
+    let investor = env.create_investor(STAKE_AMOUNT).await;
+
+    let admin = clone_keypair(&env.admin);
+    let rate_ix = env.set_reward_exchange_rate_ix(3, 2);
+    env.process(&[rate_ix], &[&admin]).await.unwrap();
+
+    let stake_ix = env.stake_ix(&investor, STAKE_AMOUNT);
+    env.process(&[stake_ix], &[&investor.keypair]).await.unwrap();
+
+    env.set_time(START_TIME + 10 * ONE_DAY).await;
+    let harvest_ix = env.harvest_ix(&investor);
+    env.process(&[harvest_ix], &[&investor.keypair]).await.unwrap();
+
+    let pending: PendingRewardWithdrawl = env.account(&investor.user_pending_withdrawl).await;
+    assert_eq!(
+        pending.pending_rewards,
+        expected_reward(STAKE_AMOUNT, 10 * ONE_DAY) * 3 / 2
+    );
+}
+
+#[tokio::test]
+async fn reward_exchange_rate_is_locked_once_staked() {
+    let mut env = TestEnv::new().await;
+    let investor = env.create_investor(STAKE_AMOUNT).await;
+
+    let admin = clone_keypair(&env.admin);
+    let rate_ix = env.set_reward_exchange_rate_ix(1, 0);
+    let result = env.process(&[rate_ix], &[&admin]).await;
+    assert_program_error(result, ProgramErrorCode::InvalidRewardExchangeRate);
+
+    let stake_ix = env.stake_ix(&investor, STAKE_AMOUNT);
+    env.process(&[stake_ix], &[&investor.keypair]).await.unwrap();
+
+    let rate_ix = env.set_reward_exchange_rate_ix(2, 1);
+    let result = env.process(&[rate_ix], &[&admin]).await;
+    assert_program_error(result, ProgramErrorCode::RewardExchangeRateLocked);
+}
+
+#[tokio::test]
+async fn emission_rewards_are_shared_by_stake() {
+    let mut env = TestEnv::new().await;
+    let first = env.create_investor(STAKE_AMOUNT).await;
+    let second = env.create_investor(STAKE_AMOUNT).await;
+    let reward_per_second = 1_000;
+
+    let admin = clone_keypair(&env.admin);
+    let emission_ix = env.configure_emission_ix(reward_per_second, START_TIME + 10 * ONE_DAY);
+    env.process(&[emission_ix], &[&admin]).await.unwrap();
+
+    let stake_ix = env.stake_ix(&first, STAKE_AMOUNT);
+    env.process(&[stake_ix], &[&first.keypair]).await.unwrap();
+
+    env.set_time(START_TIME + ONE_DAY).await;
+    let stake_ix = env.stake_ix(&second, STAKE_AMOUNT);
+    env.process(&[stake_ix], &[&second.keypair]).await.unwrap();
+
+    env.set_time(START_TIME + 2 * ONE_DAY).await;
+    let first_harvest_ix = env.harvest_ix(&first);
+    let second_harvest_ix = env.harvest_ix(&second);
+    env.process(
+        &[first_harvest_ix, second_harvest_ix],
+        &[&first.keypair, &second.keypair],
+    )
+    .await
+    .unwrap();
+
+    let daily_emission = u128::from(reward_per_second) * ONE_DAY as u128 * u128::from(DIV_PRECISION);
+
+    let pending: PendingRewardWithdrawl = env.account(&first.user_pending_withdrawl).await;
+    assert_eq!(pending.pending_rewards, daily_emission + daily_emission / 2);
+
+    let pending: PendingRewardWithdrawl = env.account(&second.user_pending_withdrawl).await;
+    assert_eq!(pending.pending_rewards, daily_emission / 2);
+}
+
+#[tokio::test]
+async fn emission_budget_must_be_funded() {
+    let mut env = TestEnv::new().await;
+
+    let admin = clone_keypair(&env.admin);
+    let emission_ix = env.configure_emission_ix(DISTRIBUTOR_FUNDS, START_TIME + 2);
+    let result = env.process(&[emission_ix], &[&admin]).await;
+
+    assert_program_error(result, ProgramErrorCode::InsufficientRewardFunds);
+}
+
+#[tokio::test]
+async fn emission_budget_includes_unharvested_emission() {
+    let mut env = TestEnv::new().await;
+    let investor = env.create_investor(STAKE_AMOUNT).await;
+    let emission_end = START_TIME + 10 * ONE_DAY;
+
+    let admin = clone_keypair(&env.admin);
+    let emission_ix = env.configure_emission_ix(1_000_000, emission_end);
+    env.process(&[emission_ix], &[&admin]).await.unwrap();
+
+    let stake_ix = env.stake_ix(&investor, STAKE_AMOUNT);
+    env.process(&[stake_ix], &[&investor.keypair]).await.unwrap();
+
+    // Half the budget is emitted, the raised rate only fits next to it if
+    // the emitted rewards are ignored
+    env.set_time(START_TIME + 5 * ONE_DAY).await;
+    let emission_ix = env.configure_emission_ix(2_000_000, emission_end);
+    let result = env.process(&[emission_ix], &[&admin]).await;
+    assert_program_error(result, ProgramErrorCode::InsufficientRewardFunds);
+
+    let emission_ix = env.configure_emission_ix(1_000_000, emission_end);
+    env.process(&[emission_ix], &[&admin]).await.unwrap();
+
+    let term: RuinStakingTerm = env.ruin_staking_term_account().await;
+    assert_eq!(
+        term.emitted_unharvested,
+        1_000_000 * 5 * ONE_DAY as u128 * u128::from(DIV_PRECISION)
+    );
+
+    let harvest_ix = env.harvest_ix(&investor);
+    env.process(&[harvest_ix], &[&investor.keypair]).await.unwrap();
+
+    let term: RuinStakingTerm = env.ruin_staking_term_account().await;
+    assert_eq!(term.emitted_unharvested, 0);
+}
+
+#[tokio::test]
+async fn harvest_after_lock_end_follows_post_lock_accrual() {
+    let mut env = TestEnv::new().await;
+    let investor = env.create_investor(STAKE_AMOUNT).await;
+
+    let admin = clone_keypair(&env.admin);
+    let accrual_ix = env.set_post_lock_accrual_ix(PostLockAccrual::PostLockApr, APR / 2);
+    env.process(&[accrual_ix], &[&admin]).await.unwrap();
+
+    let stake_ix = env.stake_ix(&investor, STAKE_AMOUNT);
+    env.process(&[stake_ix], &[&investor.keypair]).await.unwrap();
+
+    env.set_time(START_TIME + LOCK_DURATION + 2 * ONE_DAY).await;
+
+    let harvest_ix = env.harvest_ix(&investor);
+    env.process(&[harvest_ix], &[&investor.keypair]).await.unwrap();
+
+    let pending: PendingRewardWithdrawl = env.account(&investor.user_pending_withdrawl).await;
+    assert_eq!(
+        pending.pending_rewards,
+        expected_reward(STAKE_AMOUNT, LOCK_DURATION) + expected_reward(STAKE_AMOUNT, ONE_DAY)
+    );
+}
+
+#[tokio::test]
+async fn set_post_lock_accrual_requires_post_lock_apr() {
+    let mut env = TestEnv::new().await;
+
+    let admin = clone_keypair(&env.admin);
+    let accrual_ix = env.set_post_lock_accrual_ix(PostLockAccrual::PostLockApr, 0);
+    let result = env.process(&[accrual_ix], &[&admin]).await;
+
+    assert_program_error(result, ProgramErrorCode::InvalidStakingAPR);
+}
+
+#[tokio::test]
+async fn compound_restakes_whole_tokens_and_keeps_remainder() {
+    let mut env = TestEnv::new().await;
+    let investor = env.create_investor(STAKE_AMOUNT).await;
+
+    let stake_ix = env.stake_ix(&investor, STAKE_AMOUNT);
+    env.process(&[stake_ix], &[&investor.keypair]).await.unwrap();
+
+    env.set_time(START_TIME + 10 * ONE_DAY).await;
+    let compound_ix = env.compound_ix(&investor);
+    env.process(&[compound_ix], &[&investor.keypair])
+        .await
+        .unwrap();
+
+    let reward = expected_reward(STAKE_AMOUNT, 10 * ONE_DAY);
+    let compounded = (reward / u128::from(DIV_PRECISION)) as u64;
+
+    let user_staked: UserStaked = env.account(&investor.user_staked).await;
+    assert_eq!(user_staked.total_staked, STAKE_AMOUNT + compounded);
+    assert_eq!(user_staked.join_time, START_TIME);
+
+    let pending: PendingRewardWithdrawl = env.account(&investor.user_pending_withdrawl).await;
+    assert_eq!(pending.pending_rewards, reward % u128::from(DIV_PRECISION));
+    assert_eq!(pending.claimable_at, 0);
+
+    assert_eq!(
+        env.token_balance(&env.treasury.pubkey()).await,
+        STAKE_AMOUNT + compounded
+    );
+    let distributor = env.distributor;
+    assert_eq!(
+        env.token_balance(&distributor).await,
+        DISTRIBUTOR_FUNDS - compounded
+    );
+}
+
+#[tokio::test]
+async fn compound_harvests_whole_tokens_above_maximum() {
+    let mut env = TestEnv::new().await;
+    let investor = env.create_investor(MAXIMUM_STAKING_AMOUNT).await;
+
+    let reserved = expected_reward(MAXIMUM_STAKING_AMOUNT, LOCK_DURATION);
+    env.fund_distributor((reserved / u128::from(DIV_PRECISION)) as u64)
+        .await;
+
+    let stake_ix = env.stake_ix(&investor, MAXIMUM_STAKING_AMOUNT);
+    env.process(&[stake_ix], &[&investor.keypair]).await.unwrap();
+
+    // Nothing can be restaked, the rewards go through the harvest cooldown
+    let compound_time = START_TIME + 10 * ONE_DAY;
+    env.set_time(compound_time).await;
+    let compound_ix = env.compound_ix(&investor);
+    let claim_ix = env.claim_pending_reward_ix(&investor);
+    env.process(&[compound_ix, claim_ix], &[&investor.keypair])
+        .await
+        .unwrap();
+
+    assert_eq!(env.token_balance(&investor.token_account).await, 0);
+    let pending: PendingRewardWithdrawl = env.account(&investor.user_pending_withdrawl).await;
+    assert_eq!(
+        pending.pending_rewards,
+        expected_reward(MAXIMUM_STAKING_AMOUNT, 10 * ONE_DAY)
+    );
+    assert_eq!(pending.claimable_at, compound_time + DELAY_DURATION);
+}
+
+#[tokio::test]
+async fn compound_rejects_post_lock_rewards_the_distributor_cannot_cover() {
+    let mut env = TestEnv::new().await;
+    env.use_unfunded_term(1).await;
+    let investor = env.create_investor(STAKE_AMOUNT).await;
+
+    let admin = clone_keypair(&env.admin);
+    let accrual_ix = env.set_post_lock_accrual_ix(PostLockAccrual::SameApr, 0);
+    env.process(&[accrual_ix], &[&admin]).await.unwrap();
+
+    // Enough for the rewards reserved until lock end, nothing after it
+    let reserved =
+        (expected_reward(STAKE_AMOUNT, LOCK_DURATION) / u128::from(DIV_PRECISION)) as u64 + 1;
+    env.fund_distributor(reserved).await;
+
+    let stake_ix = env.stake_ix(&investor, STAKE_AMOUNT);
+    env.process(&[stake_ix], &[&investor.keypair]).await.unwrap();
+
+    env.set_time(START_TIME + 2 * LOCK_DURATION).await;
+    let compound_ix = env.compound_ix(&investor);
+    let result = env.process(&[compound_ix], &[&investor.keypair]).await;
+    assert_program_error(result, ProgramErrorCode::InsufficientRewardFunds);
+}
+
+#[tokio::test]
+async fn claim_pending_reward_rejects_another_pools_vault_authority() {
+    let mut env = TestEnv::new().await;
+    let investor = env.create_investor(STAKE_AMOUNT).await;
+    let distributor = env.distributor;
+    assert_eq!(env.token_owner(&distributor).await, env.vault_authority);
+
+    let stake_ix = env.stake_ix(&investor, STAKE_AMOUNT);
+    env.process(&[stake_ix], &[&investor.keypair]).await.unwrap();
+
+    env.set_time(START_TIME + 10 * ONE_DAY).await;
+    let harvest_ix = env.harvest_ix(&investor);
+    env.process(&[harvest_ix], &[&investor.keypair]).await.unwrap();
+
+    env.set_time(START_TIME + 10 * ONE_DAY + DELAY_DURATION).await;
+    let other_pool = pool_address(&env.mint.pubkey(), &env.admin.pubkey(), POOL_ID + 1);
+    let mut claim_ix = env.claim_pending_reward_ix(&investor);
+    claim_ix.accounts[2].pubkey = vault_authority_address(&other_pool);
+    let result = env.process(&[claim_ix], &[&investor.keypair]).await;
+    assert_anchor_error(result, ErrorCode::ConstraintSeeds);
+    assert_eq!(env.token_balance(&investor.token_account).await, 0);
+}
+
+#[tokio::test]
+async fn claim_pending_reward_waits_for_delay() {
+    let mut env = TestEnv::new().await;
+    let investor = env.create_investor(STAKE_AMOUNT).await;
+
+    let stake_ix = env.stake_ix(&investor, STAKE_AMOUNT);
+    env.process(&[stake_ix], &[&investor.keypair]).await.unwrap();
+
+    env.set_time(START_TIME + 10 * ONE_DAY).await;
+    let harvest_ix = env.harvest_ix(&investor);
+    env.process(&[harvest_ix], &[&investor.keypair]).await.unwrap();
+
+    let claim_ix = env.claim_pending_reward_ix(&investor);
+    env.process(std::slice::from_ref(&claim_ix), &[&investor.keypair])
+        .await
+        .unwrap();
+    assert_eq!(env.token_balance(&investor.token_account).await, 0);
+
+    env.set_time(START_TIME + 10 * ONE_DAY + DELAY_DURATION).await;
+    env.process(&[claim_ix], &[&investor.keypair]).await.unwrap();
+
+    let reward = expected_reward(STAKE_AMOUNT, 10 * ONE_DAY);
+    let claimed = (reward / u128::from(DIV_PRECISION)) as u64;
+    assert_eq!(env.token_balance(&investor.token_account).await, claimed);
+    let distributor = env.distributor;
+    assert_eq!(
+        env.token_balance(&distributor).await,
+        DISTRIBUTOR_FUNDS - claimed
+    );
+
+    let pending: PendingRewardWithdrawl = env.account(&investor.user_pending_withdrawl).await;
+    assert_eq!(pending.pending_rewards, reward % u128::from(DIV_PRECISION));
+
+    let term: RuinStakingTerm = env.ruin_staking_term_account().await;
+    assert_eq!(
+        term.total_rewards_paid,
+        u128::from(claimed) * u128::from(DIV_PRECISION)
+    );
+}
+
+#[tokio::test]
+async fn reward_streams_settle_on_harvest_and_claim() {
+    let mut env = TestEnv::new().await;
+    let investor = env.create_investor(STAKE_AMOUNT).await;
+    let reward_per_second = 1_000;
+
+    let admin = clone_keypair(&env.admin);
+    let add_stream_ix = env.add_reward_stream_ix(
+        0,
+        RewardMode::Emission,
+        0,
+        reward_per_second,
+        START_TIME + 10 * ONE_DAY,
+    );
+    env.process(&[add_stream_ix], &[&admin]).await.unwrap();
+
+    let stream_funds = 10_000_000_000;
+    let funder_token_account = env.create_token_account(&admin.pubkey()).await;
+    env.mint_to(&funder_token_account, stream_funds).await;
+    let fund_stream_ix = env.fund_reward_stream_ix(0, &funder_token_account, stream_funds);
+    env.process(&[fund_stream_ix], &[&admin]).await.unwrap();
+
+    let join_ix = env.join_reward_stream_ix(0, &investor);
+    let mut stake_ix = env.stake_ix(&investor, STAKE_AMOUNT);
+    stake_ix.accounts.extend(env.reward_stream_metas(&investor, 1));
+    env.process(&[join_ix, stake_ix], &[&investor.keypair])
+        .await
+        .unwrap();
+
+    env.set_time(START_TIME + ONE_DAY).await;
+    let mut harvest_ix = env.harvest_ix(&investor);
+    harvest_ix.accounts.extend(env.reward_stream_metas(&investor, 1));
+    env.process(&[harvest_ix], &[&investor.keypair]).await.unwrap();
+
+    let stream_reward = u128::from(reward_per_second) * ONE_DAY as u128 * u128::from(DIV_PRECISION);
+    let user_reward_stream: UserRewardStream = env
+        .account(&env.user_reward_stream(0, &investor))
+        .await;
+    assert_eq!(user_reward_stream.pending_rewards, stream_reward);
+
+    env.set_time(START_TIME + ONE_DAY + DELAY_DURATION).await;
+    let mut claim_ix = env.claim_pending_reward_ix(&investor);
+    claim_ix.accounts.extend(env.claim_reward_stream_metas(&investor, 1));
+    env.process(&[claim_ix], &[&investor.keypair]).await.unwrap();
+
+    let claimed = (expected_reward(STAKE_AMOUNT, ONE_DAY) / u128::from(DIV_PRECISION)) as u64;
+    let stream_claimed = (stream_reward / u128::from(DIV_PRECISION)) as u64;
+    assert_eq!(
+        env.token_balance(&investor.token_account).await,
+        claimed + stream_claimed
+    );
+    assert_eq!(
+        env.token_balance(&env.reward_stream_distributor(0)).await,
+        stream_funds - stream_claimed
+    );
+
+    let reward_stream: RewardStream = env.account(&env.reward_stream(0)).await;
+    assert_eq!(reward_stream.total_rewards_paid, stream_reward);
+}
+
+#[tokio::test]
+async fn stake_requires_every_reward_stream() {
+    let mut env = TestEnv::new().await;
+    let investor = env.create_investor(STAKE_AMOUNT).await;
+
+    let admin = clone_keypair(&env.admin);
+    let add_stream_ix = env.add_reward_stream_ix(
+        0,
+        RewardMode::FixedApr,
+        APR,
+        0,
+        START_TIME + 10 * ONE_DAY,
+    );
+    env.process(&[add_stream_ix], &[&admin]).await.unwrap();
+
+    let stake_ix = env.stake_ix(&investor, STAKE_AMOUNT);
+    let result = env.process(&[stake_ix], &[&investor.keypair]).await;
+
+    assert_program_error(result, ProgramErrorCode::InvalidRewardStream);
+}
+
+#[tokio::test]
+async fn withdraw_rejects_during_lock() {
+    let mut env = TestEnv::new().await;
+    let investor = env.create_investor(STAKE_AMOUNT).await;
+
+    let stake_ix = env.stake_ix(&investor, STAKE_AMOUNT);
+    env.process(&[stake_ix], &[&investor.keypair]).await.unwrap();
+
+    env.set_time(START_TIME + 10 * ONE_DAY).await;
+    let withdraw_ix = env.withdraw_ix(&investor, STAKE_AMOUNT);
+    let result = env.process(&[withdraw_ix], &[&investor.keypair]).await;
+
+    assert_program_error(result, ProgramErrorCode::StakingLocked);
+
+    let user_staked: UserStaked = env.account(&investor.user_staked).await;
+    assert_eq!(user_staked.total_staked, STAKE_AMOUNT);
+}
+
+#[tokio::test]
+async fn early_withdraw_requires_enabled_term() {
+    let mut env = TestEnv::new().await;
+    let investor = env.create_investor(STAKE_AMOUNT).await;
+
+    let stake_ix = env.stake_ix(&investor, STAKE_AMOUNT);
+    env.process(&[stake_ix], &[&investor.keypair]).await.unwrap();
+
+    let treasury = env.treasury.pubkey();
+    let early_withdraw_ix = env.early_withdraw_ix(&investor, &treasury, STAKE_AMOUNT);
+    let result = env.process(&[early_withdraw_ix], &[&investor.keypair]).await;
+
+    assert_program_error(result, ProgramErrorCode::EarlyWithdrawDisabled);
+}
+
+#[tokio::test]
+async fn early_withdraw_routes_penalty_to_distributor() {
+    let mut env = TestEnv::new().await;
+    let investor = env.create_investor(STAKE_AMOUNT).await;
+    let distributor = env.distributor;
+
+    let admin = clone_keypair(&env.admin);
+    let config_ix =
+        env.set_early_withdraw_config_ix(1000, 5000, PenaltyDestination::Distributor, &distributor);
+    env.process(&[config_ix], &[&admin]).await.unwrap();
+
+    let stake_ix = env.stake_ix(&investor, STAKE_AMOUNT);
+    env.process(&[stake_ix], &[&investor.keypair]).await.unwrap();
+
+    env.set_time(START_TIME + 10 * ONE_DAY).await;
+    let early_withdraw_ix = env.early_withdraw_ix(&investor, &distributor, STAKE_AMOUNT);
+    env.process(&[early_withdraw_ix], &[&investor.keypair])
+        .await
+        .unwrap();
+
+    let principal_penalty = STAKE_AMOUNT / 10;
+    assert_eq!(
+        env.token_balance(&investor.token_account).await,
+        STAKE_AMOUNT - principal_penalty
+    );
+    assert_eq!(
+        env.token_balance(&distributor).await,
+        DISTRIBUTOR_FUNDS + principal_penalty
+    );
+
+    let pending: PendingRewardWithdrawl = env.account(&investor.user_pending_withdrawl).await;
+    let reward = expected_reward(STAKE_AMOUNT, 10 * ONE_DAY);
+    let withdrawn_rewards = reward / u128::from(STAKE_AMOUNT) * u128::from(STAKE_AMOUNT);
+    assert_eq!(pending.pending_rewards, reward - withdrawn_rewards / 2);
+
+    let term: RuinStakingTerm = env.ruin_staking_term_account().await;
+    assert_eq!(term.total_staked, 0);
+    assert_eq!(term.active_stakers, 0);
+    assert_eq!(term.reward_liability, pending.pending_rewards);
+}
+
+#[tokio::test]
+async fn early_withdraw_routes_penalty_to_fee_account() {
+    let mut env = TestEnv::new().await;
+    let investor = env.create_investor(STAKE_AMOUNT).await;
+    let fee_owner = Keypair::new();
+    let fee_account = env.create_token_account(&fee_owner.pubkey()).await;
+
+    let admin = clone_keypair(&env.admin);
+    let config_ix =
+        env.set_early_withdraw_config_ix(2500, 0, PenaltyDestination::FeeAccount, &fee_account);
+    env.process(&[config_ix], &[&admin]).await.unwrap();
+
+    let stake_ix = env.stake_ix(&investor, STAKE_AMOUNT);
+    env.process(&[stake_ix], &[&investor.keypair]).await.unwrap();
+
+    // The penalty can't be redirected to another account
+    let treasury = env.treasury.pubkey();
+    let early_withdraw_ix = env.early_withdraw_ix(&investor, &treasury, STAKE_AMOUNT / 2);
+    let result = env.process(&[early_withdraw_ix], &[&investor.keypair]).await;
+    assert_program_error(result, ProgramErrorCode::WrongPenaltyAddress);
+
+    let early_withdraw_ix = env.early_withdraw_ix(&investor, &fee_account, STAKE_AMOUNT / 2);
+    env.process(&[early_withdraw_ix], &[&investor.keypair])
+        .await
+        .unwrap();
+
+    assert_eq!(env.token_balance(&fee_account).await, STAKE_AMOUNT / 8);
+    assert_eq!(
+        env.token_balance(&investor.token_account).await,
+        STAKE_AMOUNT / 2 - STAKE_AMOUNT / 8
+    );
+
+    let user_staked: UserStaked = env.account(&investor.user_staked).await;
+    assert_eq!(user_staked.total_staked, STAKE_AMOUNT / 2);
+}
+
+#[tokio::test]
+async fn treasury_penalties_can_be_swept_by_admin() {
+    let mut env = TestEnv::new().await;
+    let investor = env.create_investor(STAKE_AMOUNT).await;
+    let other = env.create_investor(STAKE_AMOUNT).await;
+    let treasury = env.treasury.pubkey();
+
+    let admin = clone_keypair(&env.admin);
+    let config_ix = env.set_early_withdraw_config_ix(1000, 0, PenaltyDestination::Treasury, &treasury);
+    env.process(&[config_ix], &[&admin]).await.unwrap();
+
+    let stake_ix = env.stake_ix(&investor, STAKE_AMOUNT);
+    let other_stake_ix = env.stake_ix(&other, STAKE_AMOUNT);
+    env.process(&[stake_ix, other_stake_ix], &[&investor.keypair, &other.keypair])
+        .await
+        .unwrap();
+
+    let early_withdraw_ix = env.early_withdraw_ix(&investor, &treasury, STAKE_AMOUNT);
+    env.process(&[early_withdraw_ix], &[&investor.keypair])
+        .await
+        .unwrap();
+
+    let ruin_staking: RuinStaking = env.ruin_staking_account().await;
+    assert_eq!(ruin_staking.penalty_collected, STAKE_AMOUNT / 10);
+
+    let recipient = env.create_token_account(&admin.pubkey()).await;
+    let mut sweep_ix = env.sweep_penalties_ix(&recipient);
+    sweep_ix.accounts[4].pubkey = investor.keypair.pubkey();
+    let result = env.process(&[sweep_ix], &[&investor.keypair]).await;
+    assert_program_error(result, ProgramErrorCode::InvalidStakingAdmin);
+
+    let sweep_ix = env.sweep_penalties_ix(&recipient);
+    env.process(&[sweep_ix], &[&admin]).await.unwrap();
+
+    // Only the penalty leaves, the other stake stays backed
+    assert_eq!(env.token_balance(&recipient).await, STAKE_AMOUNT / 10);
+    assert_eq!(env.token_balance(&treasury).await, STAKE_AMOUNT);
+    let ruin_staking: RuinStaking = env.ruin_staking_account().await;
+    assert_eq!(ruin_staking.penalty_collected, 0);
+}
+
+#[tokio::test]
+async fn early_withdraw_rejects_after_lock() {
+    let mut env = TestEnv::new().await;
+    let investor = env.create_investor(STAKE_AMOUNT).await;
+    let treasury = env.treasury.pubkey();
+
+    let admin = clone_keypair(&env.admin);
+    let config_ix = env.set_early_withdraw_config_ix(1000, 0, PenaltyDestination::Treasury, &treasury);
+    env.process(&[config_ix], &[&admin]).await.unwrap();
+
+    let stake_ix = env.stake_ix(&investor, STAKE_AMOUNT);
+    env.process(&[stake_ix], &[&investor.keypair]).await.unwrap();
+
+    env.set_time(START_TIME + LOCK_DURATION + 1).await;
+    let early_withdraw_ix = env.early_withdraw_ix(&investor, &treasury, STAKE_AMOUNT);
+    let result = env.process(&[early_withdraw_ix], &[&investor.keypair]).await;
+
+    assert_program_error(result, ProgramErrorCode::StakingUnlocked);
+}
+
+#[tokio::test]
+async fn withdraw_can_be_repeated() {
+    let mut env = TestEnv::new().await;
+    let investor = env.create_investor(STAKE_AMOUNT).await;
+
+    let stake_ix = env.stake_ix(&investor, STAKE_AMOUNT);
+    env.process(&[stake_ix], &[&investor.keypair]).await.unwrap();
+
+    env.set_time(START_TIME + LOCK_DURATION + 1).await;
+    let withdraw_ix = env.withdraw_ix(&investor, STAKE_AMOUNT / 2);
+    env.process(&[withdraw_ix], &[&investor.keypair])
+        .await
+        .unwrap();
+
+    // The queued tokens have to be claimed first, so that neither withdrawal
+    // shortens or extends the other's lock
+    env.set_time(START_TIME + LOCK_DURATION + 2).await;
+    let withdraw_ix = env.withdraw_ix(&investor, STAKE_AMOUNT / 2);
+    let result = env
+        .process(std::slice::from_ref(&withdraw_ix), &[&investor.keypair])
+        .await;
+    assert_program_error(result, ProgramErrorCode::WithdrawalAlreadyQueued);
+
+    let second_withdraw_time = START_TIME + 2 * LOCK_DURATION + 1;
+    env.set_time(second_withdraw_time).await;
+    let claim_ix = env.claim_pending_withdrawl_ix(&investor);
+    env.process(&[claim_ix, withdraw_ix], &[&investor.keypair])
+        .await
+        .unwrap();
+
+    let pending: PendingWithdrawl = env.account(&investor.user_pending_withdraw).await;
+    assert_eq!(pending.pending_tokens, STAKE_AMOUNT / 2);
+    assert_eq!(pending.claimable_at, second_withdraw_time + LOCK_DURATION);
+    assert_eq!(env.token_balance(&investor.token_account).await, STAKE_AMOUNT / 2);
+
+    let user_staked: UserStaked = env.account(&investor.user_staked).await;
+    assert_eq!(user_staked.total_staked, 0);
+}
+
+#[tokio::test]
+async fn close_position_requires_empty_position() {
+    let mut env = TestEnv::new().await;
+    let investor = env.create_investor(STAKE_AMOUNT).await;
+
+    let stake_ix = env.stake_ix(&investor, STAKE_AMOUNT);
+    env.process(&[stake_ix], &[&investor.keypair]).await.unwrap();
+
+    let close_ix = env.close_position_ix(&investor);
+    let result = env.process(&[close_ix], &[&investor.keypair]).await;
+
+    assert_program_error(result, ProgramErrorCode::PositionNotEmpty);
+}
+
+#[tokio::test]
+async fn close_position_closes_user_accounts() {
+    let mut env = TestEnv::new().await;
+    let investor = env.create_investor(STAKE_AMOUNT).await;
+
+    let stake_ix = env.stake_ix(&investor, STAKE_AMOUNT);
+    env.process(&[stake_ix], &[&investor.keypair]).await.unwrap();
+
+    // Claiming leaves at most sub-token reward dust behind
+    env.set_time(START_TIME + LOCK_DURATION + 1).await;
+    let withdraw_ix = env.withdraw_ix(&investor, STAKE_AMOUNT);
+    env.process(&[withdraw_ix], &[&investor.keypair])
+        .await
+        .unwrap();
+
+    let pending: PendingRewardWithdrawl = env.account(&investor.user_pending_withdrawl).await;
+    env.set_time(pending.claimable_at + 2 * LOCK_DURATION).await;
+    let claim_reward_ix = env.claim_pending_reward_ix(&investor);
+    let claim_principal_ix = env.claim_pending_withdrawl_ix(&investor);
+    env.process(&[claim_reward_ix, claim_principal_ix], &[&investor.keypair])
+        .await
+        .unwrap();
+
+    let close_ix = env.close_position_ix(&investor);
+    env.process(&[close_ix], &[&investor.keypair]).await.unwrap();
+
+    for address in [
+        investor.user_staked,
+        investor.user_pending_withdrawl,
+        investor.user_pending_withdraw,
+    ] {
+        assert!(env
+            .context
+            .banks_client
+            .get_account(address)
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    let term: RuinStakingTerm = env.ruin_staking_term_account().await;
+    assert_eq!(term.reward_liability, 0);
+}
+
+#[tokio::test]
+async fn emergency_withdraw_requires_pause() {
+    let mut env = TestEnv::new().await;
+    let investor = env.create_investor(STAKE_AMOUNT).await;
+
+    let stake_ix = env.stake_ix(&investor, STAKE_AMOUNT);
+    env.process(&[stake_ix], &[&investor.keypair]).await.unwrap();
+
+    let emergency_ix = env.emergency_withdraw_ix(&investor);
+    let result = env.process(&[emergency_ix], &[&investor.keypair]).await;
+
+    assert_program_error(result, ProgramErrorCode::StakingNotPaused);
+}
+
+#[tokio::test]
+async fn emergency_withdraw_returns_principal_and_forfeits_rewards() {
+    let mut env = TestEnv::new().await;
+    let investor = env.create_investor(STAKE_AMOUNT).await;
+
+    let stake_ix = env.stake_ix(&investor, STAKE_AMOUNT);
+    env.process(&[stake_ix], &[&investor.keypair]).await.unwrap();
+
+    env.set_time(START_TIME + 10 * ONE_DAY).await;
+    let harvest_ix = env.harvest_ix(&investor);
+    env.process(&[harvest_ix], &[&investor.keypair]).await.unwrap();
+
+    let admin = clone_keypair(&env.admin);
+    let pause_ix = env.set_paused_ix(true);
+    env.process(&[pause_ix], &[&admin]).await.unwrap();
+
+    let emergency_ix = env.emergency_withdraw_ix(&investor);
+    env.process(&[emergency_ix], &[&investor.keypair])
+        .await
+        .unwrap();
+
+    assert_eq!(env.token_balance(&investor.token_account).await, STAKE_AMOUNT);
+    assert!(env
+        .context
+        .banks_client
+        .get_account(investor.user_staked)
+        .await
+        .unwrap()
+        .is_none());
+    assert!(env
+        .context
+        .banks_client
+        .get_account(investor.user_pending_withdrawl)
+        .await
+        .unwrap()
+        .is_none());
+
+    let term: RuinStakingTerm = env.ruin_staking_term_account().await;
+    assert_eq!(term.total_staked, 0);
+    assert_eq!(term.active_stakers, 0);
+    assert_eq!(term.reward_liability, 0);
+
+    let ruin_staking: RuinStaking = env.ruin_staking_account().await;
+    assert_eq!(ruin_staking.total_staked, 0);
+}
+
+#[tokio::test]
+async fn close_position_requires_empty_stream_positions() {
+    let mut env = TestEnv::new().await;
+    let investor = env.create_investor(STAKE_AMOUNT).await;
+
+    let admin = clone_keypair(&env.admin);
+    let add_stream_ix =
+        env.add_reward_stream_ix(0, RewardMode::Emission, 0, 1_000, START_TIME + LOCK_DURATION);
+    env.process(&[add_stream_ix], &[&admin]).await.unwrap();
+
+    let join_ix = env.join_reward_stream_ix(0, &investor);
+    let mut stake_ix = env.stake_ix(&investor, STAKE_AMOUNT);
+    stake_ix.accounts.extend(env.reward_stream_metas(&investor, 1));
+    env.process(&[join_ix, stake_ix], &[&investor.keypair])
+        .await
+        .unwrap();
+
+    let withdraw_time = START_TIME + LOCK_DURATION + 1;
+    env.set_time(withdraw_time).await;
+    let mut withdraw_ix = env.withdraw_ix(&investor, STAKE_AMOUNT);
+    withdraw_ix.accounts.extend(env.reward_stream_metas(&investor, 1));
+    env.process(&[withdraw_ix], &[&investor.keypair])
+        .await
+        .unwrap();
+
+    // The unfunded stream keeps its rewards pending
+    env.set_time(withdraw_time + 2 * LOCK_DURATION).await;
+    let mut claim_reward_ix = env.claim_pending_reward_ix(&investor);
+    claim_reward_ix.accounts.extend(env.claim_reward_stream_metas(&investor, 1));
+    let claim_principal_ix = env.claim_pending_withdrawl_ix(&investor);
+    env.process(&[claim_reward_ix, claim_principal_ix], &[&investor.keypair])
+        .await
+        .unwrap();
+
+    let close_ix = env.close_position_ix(&investor);
+    let result = env.process(&[close_ix], &[&investor.keypair]).await;
+    assert_program_error(result, ProgramErrorCode::InvalidRewardStream);
+
+    let mut close_ix = env.close_position_ix(&investor);
+    close_ix.accounts.extend(env.user_reward_stream_metas(&investor, 1));
+    let result = env.process(&[close_ix], &[&investor.keypair]).await;
+    assert_program_error(result, ProgramErrorCode::PositionNotEmpty);
+
+    let stream_funds = 10_000_000_000;
+    let funder_token_account = env.create_token_account(&admin.pubkey()).await;
+    env.mint_to(&funder_token_account, stream_funds).await;
+    let fund_stream_ix = env.fund_reward_stream_ix(0, &funder_token_account, stream_funds);
+    env.process(&[fund_stream_ix], &[&admin]).await.unwrap();
+
+    let mut claim_ix = env.claim_pending_reward_ix(&investor);
+    claim_ix.accounts.extend(env.claim_reward_stream_metas(&investor, 1));
+    let mut close_ix = env.close_position_ix(&investor);
+    close_ix.accounts.extend(env.user_reward_stream_metas(&investor, 1));
+    env.process(&[claim_ix, close_ix], &[&investor.keypair])
+        .await
+        .unwrap();
+
+    assert!(env
+        .context
+        .banks_client
+        .get_account(env.user_reward_stream(0, &investor))
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[tokio::test]
+async fn emergency_withdraw_closes_empty_user_accounts() {
+    let mut env = TestEnv::new().await;
+    let investor = env.create_investor(STAKE_AMOUNT).await;
+
+    let admin = clone_keypair(&env.admin);
+    let add_stream_ix =
+        env.add_reward_stream_ix(0, RewardMode::Emission, 0, 1_000, START_TIME + LOCK_DURATION);
+    env.process(&[add_stream_ix], &[&admin]).await.unwrap();
+
+    let join_ix = env.join_reward_stream_ix(0, &investor);
+    let mut stake_ix = env.stake_ix(&investor, STAKE_AMOUNT);
+    stake_ix.accounts.extend(env.reward_stream_metas(&investor, 1));
+    env.process(&[join_ix, stake_ix], &[&investor.keypair])
+        .await
+        .unwrap();
+
+    let withdraw_time = START_TIME + LOCK_DURATION + 1;
+    env.set_time(withdraw_time).await;
+    let mut withdraw_ix = env.withdraw_ix(&investor, STAKE_AMOUNT / 2);
+    withdraw_ix.accounts.extend(env.reward_stream_metas(&investor, 1));
+    env.process(&[withdraw_ix], &[&investor.keypair])
+        .await
+        .unwrap();
+
+    env.set_time(withdraw_time + LOCK_DURATION).await;
+    let claim_ix = env.claim_pending_withdrawl_ix(&investor);
+    env.process(&[claim_ix], &[&investor.keypair]).await.unwrap();
+
+    let pause_ix = env.set_paused_ix(true);
+    env.process(&[pause_ix], &[&admin]).await.unwrap();
+
+    // Stream positions are closed without the streams themselves
+    let mut emergency_ix = env.emergency_withdraw_ix(&investor);
+    emergency_ix.accounts.extend(env.reward_stream_metas(&investor, 1));
+    let result = env.process(&[emergency_ix], &[&investor.keypair]).await;
+    assert_program_error(result, ProgramErrorCode::InvalidRewardStream);
+
+    let reward_stream_before: RewardStream = env.account(&env.reward_stream(0)).await;
+    let mut emergency_ix = env.emergency_withdraw_ix(&investor);
+    emergency_ix.accounts.extend(env.user_reward_stream_metas(&investor, 1));
+    env.process(&[emergency_ix], &[&investor.keypair])
+        .await
+        .unwrap();
+
+    assert_eq!(env.token_balance(&investor.token_account).await, STAKE_AMOUNT);
+    for account in [
+        investor.user_staked,
+        investor.user_pending_withdrawl,
+        investor.user_pending_withdraw,
+        env.user_reward_stream(0, &investor),
+    ] {
+        assert!(env
+            .context
+            .banks_client
+            .get_account(account)
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    let reward_stream: RewardStream = env.account(&env.reward_stream(0)).await;
+    assert_eq!(reward_stream.last_reward_time, reward_stream_before.last_reward_time);
+}
+
+#[tokio::test]
+async fn withdraw_and_claim_principal_after_lock() {
+    let mut env = TestEnv::new().await;
+    let investor = env.create_investor(STAKE_AMOUNT).await;
+
+    let stake_ix = env.stake_ix(&investor, STAKE_AMOUNT);
+    env.process(&[stake_ix], &[&investor.keypair]).await.unwrap();
+
+    let withdraw_time = START_TIME + LOCK_DURATION + 1;
+    env.set_time(withdraw_time).await;
+    let withdraw_ix = env.withdraw_ix(&investor, STAKE_AMOUNT);
+    env.process(&[withdraw_ix], &[&investor.keypair])
+        .await
+        .unwrap();
+
+    let user_staked: UserStaked = env.account(&investor.user_staked).await;
+    assert_eq!(user_staked.total_staked, 0);
+
+    let pending: PendingWithdrawl = env.account(&investor.user_pending_withdraw).await;
+    assert_eq!(pending.pending_tokens, STAKE_AMOUNT);
+    assert_eq!(pending.claimable_at, withdraw_time + LOCK_DURATION);
+
+    let ruin_staking: RuinStaking = env.ruin_staking_account().await;
+    assert_eq!(ruin_staking.total_staked, 0);
+
+    let term: RuinStakingTerm = env.ruin_staking_term_account().await;
+    assert_eq!(term.total_staked, 0);
+    assert_eq!(term.active_stakers, 0);
+
+    let claim_ix = env.claim_pending_withdrawl_ix(&investor);
+    let result = env
+        .process(std::slice::from_ref(&claim_ix), &[&investor.keypair])
+        .await;
+    assert_program_error(result, ProgramErrorCode::StakingLocked);
+    assert_eq!(env.token_balance(&investor.token_account).await, 0);
+
+    env.set_time(withdraw_time + LOCK_DURATION).await;
+    env.process(std::slice::from_ref(&claim_ix), &[&investor.keypair])
+        .await
+        .unwrap();
+
+    assert_eq!(env.token_balance(&investor.token_account).await, STAKE_AMOUNT);
+    assert_eq!(env.token_balance(&env.treasury.pubkey()).await, 0);
+
+    let pending: PendingWithdrawl = env.account(&investor.user_pending_withdraw).await;
+    assert_eq!(pending.pending_tokens, 0);
+
+    env.set_time(withdraw_time + LOCK_DURATION + 1).await;
+    let result = env.process(&[claim_ix], &[&investor.keypair]).await;
+    assert_program_error(result, ProgramErrorCode::NothingToClaim);
+}
+
+#[tokio::test]
+async fn withdraw_rejects_more_than_staked() {
+    let mut env = TestEnv::new().await;
+    let investor = env.create_investor(STAKE_AMOUNT).await;
+
+    let stake_ix = env.stake_ix(&investor, STAKE_AMOUNT);
+    env.process(&[stake_ix], &[&investor.keypair]).await.unwrap();
+
+    env.set_time(START_TIME + LOCK_DURATION + 1).await;
+    let withdraw_ix = env.withdraw_ix(&investor, STAKE_AMOUNT + 1);
+    let result = env.process(&[withdraw_ix], &[&investor.keypair]).await;
+
+    assert_program_error(result, ProgramErrorCode::ExceedsCurrentStakingAmount);
+}
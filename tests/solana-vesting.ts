@@ -17,12 +17,15 @@ describe("solana-staking", () => {
 
   const LOCK_DURATION = 5;
 
-  let ruinStaking: Keypair;
-  let ruinStakingTerm: Keypair;
+  const POOL_ID = 0;
+
+  let ruinStaking: PublicKey;
+  let ruinStakingTerm: PublicKey;
   let ruinStakingTreasury: Keypair;
   let staker: Keypair;
   let deployerKeypair: Keypair;
   let vaultAuthority: PublicKey;
+  let treasuryAuthority: PublicKey;
   let userPendingWithdrawl: PublicKey;
   let userStaked: PublicKey;
   let deployer: PublicKey;
@@ -35,8 +38,6 @@ describe("solana-staking", () => {
     deployer = deployerKeypair.publicKey;
     staker = anchor.web3.Keypair.generate();
     ruinStakingTreasury = anchor.web3.Keypair.generate();
-    ruinStaking = anchor.web3.Keypair.generate();
-    ruinStakingTerm = anchor.web3.Keypair.generate();
 
     const signature = await program.provider.connection.requestAirdrop(deployer, 90000000000000);
     await program.provider.connection.confirmTransaction(signature, 'confirmed');
@@ -75,13 +76,29 @@ describe("solana-staking", () => {
       deployerKeypair
     );
 
+    [ruinStaking] = await anchor.web3.PublicKey.findProgramAddress(
+      [
+        Buffer.from("staking"),
+        stakingToken.toBuffer(),
+        deployer.toBuffer(),
+        new anchor.BN(POOL_ID).toArrayLike(Buffer, "le", 8),
+      ],
+      program.programId,
+    );
+
+    [ruinStakingTerm] = await anchor.web3.PublicKey.findProgramAddress(
+      [
+        Buffer.from("term"),
+        ruinStaking.toBuffer(),
+        Buffer.from([0]),
+      ],
+      program.programId,
+    );
 
     let [distributorPubkey] = await anchor.web3.PublicKey.findProgramAddress(
       [
         Buffer.from("distributor"),
-        stakingToken.toBuffer(),
-        deployer.toBuffer(),
-        new anchor.BN(LOCK_DURATION).toArrayLike(Buffer),
+        ruinStakingTerm.toBuffer(),
       ],
       program.programId,
     );
@@ -92,8 +109,8 @@ describe("solana-staking", () => {
     let [userStakedPubkey] = await anchor.web3.PublicKey.findProgramAddress(
       [
         Buffer.from("stake"),
-        ruinStaking.publicKey.toBuffer(),
-        ruinStakingTerm.publicKey.toBuffer(),
+        ruinStaking.toBuffer(),
+        ruinStakingTerm.toBuffer(),
         staker.publicKey.toBuffer()
       ],
       program.programId,
@@ -102,8 +119,8 @@ describe("solana-staking", () => {
     let [pendingWithdrawlPubkey] = await anchor.web3.PublicKey.findProgramAddress(
       [
         Buffer.from("withdraw_reward"),
-        ruinStaking.publicKey.toBuffer(),
-        ruinStakingTerm.publicKey.toBuffer(),
+        ruinStaking.toBuffer(),
+        ruinStakingTerm.toBuffer(),
         staker.publicKey.toBuffer()
       ],
       program.programId,
@@ -112,15 +129,26 @@ describe("solana-staking", () => {
     let [vaultAuthorityPubkey] = await anchor.web3.PublicKey.findProgramAddress(
       [
         Buffer.from("vault-authority"),
+        ruinStaking.toBuffer(),
       ],
       program.programId,
     );
 
+    let [treasuryAuthorityPubkey] = await anchor.web3.PublicKey.findProgramAddress(
+      [
+        Buffer.from("treasury-authority"),
+        ruinStaking.toBuffer(),
+      ],
+      program.programId,
+    );
+    treasuryAuthority = treasuryAuthorityPubkey;
+
     const startJoinTime = Math.floor(new Date().getTime() / 1000) - 3000;
     const endJointTime = startJoinTime + 50000;
 
     try {
       await program.rpc.initialize(
+        new anchor.BN(POOL_ID),
         new anchor.BN(10),
         new anchor.BN(200 * (10 ** 6)),
         new anchor.BN(LOCK_DURATION),
@@ -129,13 +157,17 @@ describe("solana-staking", () => {
         new anchor.BN(10),
         new anchor.BN(40000),
         new anchor.BN(5),
+        new anchor.BN(24 * 60 * 60),
         {
           accounts: {
             ruinStakingTreasury: ruinStakingTreasury.publicKey,
-            ruinStakingTerm: ruinStakingTerm.publicKey,
-            ruinStaking: ruinStaking.publicKey,
+            ruinStakingTerm: ruinStakingTerm,
+            ruinStaking: ruinStaking,
             ruinStakingAdmin: deployer,
             ruinStakingToken: stakingToken,
+            ruinStakingRewardToken: stakingToken,
+            treasuryAuthority,
+            vaultAuthority: vaultAuthorityPubkey,
             ruinStakingDistributor: distributorTokenAccount,
             systemProgram: anchor.web3.SystemProgram.programId,
             tokenProgram: spl.TOKEN_PROGRAM_ID,
@@ -144,8 +176,6 @@ describe("solana-staking", () => {
           signers: [
             deployerKeypair,
             ruinStakingTreasury,
-            ruinStaking,
-            ruinStakingTerm,
           ]
         }
       )
@@ -168,15 +198,16 @@ describe("solana-staking", () => {
   });
 
   it("Staking info system can be initialized", async () => {
-    const term = await program.account.ruinStakingTerm.fetch(ruinStakingTerm.publicKey);
+    const term = await program.account.ruinStakingTerm.fetch(ruinStakingTerm);
 
-    assert.equal(ruinStaking.publicKey.toBase58(), term.ruinStaking.toBase58());
+    assert.equal(ruinStaking.toBase58(), term.ruinStaking.toBase58());
     assert.equal(term.apr, 40000);
   });
 
   it("Staking info system can't be initialized if lacks any signature", async () => {
     try {
       await program.rpc.initialize(
+        new anchor.BN(POOL_ID),
         new anchor.BN(10),
         new anchor.BN(200),
         new anchor.BN(0),
@@ -185,13 +216,17 @@ describe("solana-staking", () => {
         new anchor.BN(5 * 60),
         new anchor.BN(40000),
         new anchor.BN(5),
+        new anchor.BN(24 * 60 * 60),
         {
           accounts: {
             ruinStakingTreasury: ruinStakingTreasury.publicKey,
-            ruinStakingTerm: ruinStakingTerm.publicKey,
-            ruinStaking: ruinStaking.publicKey,
+            ruinStakingTerm: ruinStakingTerm,
+            ruinStaking: ruinStaking,
             ruinStakingAdmin: deployer,
             ruinStakingToken: stakingToken,
+            ruinStakingRewardToken: stakingToken,
+            treasuryAuthority,
+            vaultAuthority,
             ruinStakingDistributor: distributorTokenAccount,
             systemProgram: anchor.web3.SystemProgram.programId,
             tokenProgram: spl.TOKEN_PROGRAM_ID,
@@ -209,7 +244,7 @@ describe("solana-staking", () => {
   });
 
   it("User able to stake token", async () => {
-    const staking = await program.account.ruinStaking.fetch(ruinStaking.publicKey);
+    const staking = await program.account.ruinStaking.fetch(ruinStaking);
     const balance = await program.provider.connection.getBalance(staker.publicKey);
     console.log(balance.toString());
 
@@ -217,13 +252,14 @@ describe("solana-staking", () => {
       new anchor.BN(100 * (10 ** 6)),
       {
         accounts: {
-          ruinStakingTerm: ruinStakingTerm.publicKey,
-          ruinStaking: ruinStaking.publicKey,
+          ruinStakingTerm: ruinStakingTerm,
+          ruinStaking: ruinStaking,
           investor: staker.publicKey,
           investorTokenAccount: stakerTokenAccount,
           userPendingWithdrawl,
           userStaked,
           treasuryTokenAccount: staking.treasury,
+          distributorTokenAccount,
           systemProgram: anchor.web3.SystemProgram.programId,
           tokenProgram: spl.TOKEN_PROGRAM_ID,
           rent: anchor.web3.SYSVAR_RENT_PUBKEY,
@@ -239,19 +275,20 @@ describe("solana-staking", () => {
   });
 
   it("User able to harvest reward when time's passed", async () => {
-    const staking = await program.account.ruinStaking.fetch(ruinStaking.publicKey);
+    const staking = await program.account.ruinStaking.fetch(ruinStaking);
 
     await program.rpc.stake(
       new anchor.BN(100 * (10 ** 6)),
       {
         accounts: {
-          ruinStakingTerm: ruinStakingTerm.publicKey,
-          ruinStaking: ruinStaking.publicKey,
+          ruinStakingTerm: ruinStakingTerm,
+          ruinStaking: ruinStaking,
           investor: staker.publicKey,
           investorTokenAccount: stakerTokenAccount,
           userPendingWithdrawl,
           userStaked,
           treasuryTokenAccount: staking.treasury,
+          distributorTokenAccount,
           systemProgram: anchor.web3.SystemProgram.programId,
           tokenProgram: spl.TOKEN_PROGRAM_ID,
           rent: anchor.web3.SYSVAR_RENT_PUBKEY,
@@ -267,8 +304,8 @@ describe("solana-staking", () => {
     await program.rpc.harvest(
       {
         accounts: {
-          ruinStakingTerm: ruinStakingTerm.publicKey,
-          ruinStaking: ruinStaking.publicKey,
+          ruinStakingTerm: ruinStakingTerm,
+          ruinStaking: ruinStaking,
           investor: staker.publicKey,
           userPendingWithdrawl,
           userStaked,
@@ -281,14 +318,17 @@ describe("solana-staking", () => {
         ]
       }
     )
+    // Rewards become claimable once the delay duration has passed
+    await sleep(11 * 1000);
+
     const pendingWithdrawlResp = await program.account.pendingRewardWithdrawl.fetch(userPendingWithdrawl);
     const tokenBalanceBeforeClaim = await program.provider.connection.getTokenAccountBalance(stakerTokenAccount);
 
     await program.rpc.claimPendingReward(
       {
         accounts: {
-          ruinStakingTerm: ruinStakingTerm.publicKey,
-          ruinStaking: ruinStaking.publicKey,
+          ruinStakingTerm: ruinStakingTerm,
+          ruinStaking: ruinStaking,
           vaultAuthority,
           investor: staker.publicKey,
           distributorTokenAccount,